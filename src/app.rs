@@ -1,6 +1,24 @@
-use crate::cup::scramble_cups;
+use std::time::{Duration, Instant};
+
+use crate::cup::{scramble_cups, settle};
+use crate::autoplay::Autoplay;
 use crate::cup::Cup;
-use crate::level_creator::{SimpleDifficulties, generate_cups };
+use crate::daily::{daily_seed, share_text, today, DailyRecord, Day};
+use crate::level_creator::{
+    generate_board_with_par, generate_cups, hide_layers, BoardParams, SimpleDifficulties,
+};
+use crate::difficulty::{rate, Rating};
+use crate::editor::Editor;
+use crate::level::{save_level, share_code, Level};
+use crate::highscores::{load_scores, makes_leaderboard, save_score, HighScore, MAX_NAME_LENGTH};
+use crate::orders::{is_tap, order_board, Shift};
+use crate::replay::{save_replay, Playback, Replay};
+use crate::pour_rule::{rule_from_name, PourRule, Sandbox, Standard};
+use crate::solver::{is_dead_end, legal_pours, next_reveal, solve, Pour};
+use crate::win_condition::{goal_from_name, UniqueMonochrome, WinCondition};
+use crate::stats::{
+    load_stats, record_daily, record_endless, record_game, EndlessRecord, GameRecord, Stats,
+};
 
 #[derive(Debug, Default, PartialEq, Eq)]
 pub enum CurrentScreen {
@@ -8,23 +26,1115 @@ pub enum CurrentScreen {
     Welcome,
     Main,
     Finished,
+    Statistics,
+    HighScores,
+    GameOver,
+    // No pours left, or none that lead anywhere, until one is undone
+    Stuck,
+    Editor,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    // Everyone gets the same board on the same (UTC) day
+    Daily(Day),
+    // Each board has a time limit, time left over is carried
+    // into the next board until the clock runs out
+    Timed,
+    // Each board must be solved within a few pours of par
+    Limited,
+    // Boards keep getting harder until one can't be finished
+    Endless,
+    // Customers order layered drinks that are built from the taps
+    Orders,
+    // Every layer below the top of each cup starts out hidden
+    Mystery,
+    // A board loaded from a level file
+    Custom,
+}
+
+// Entries on the welcome screen, top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Classic,
+    Daily,
+    Timed,
+    Limited,
+    Endless,
+    Mystery,
+    Orders,
+    Editor,
+}
+
+pub const MENU_ITEMS: [MenuItem; 8] = [
+    MenuItem::Classic,
+    MenuItem::Daily,
+    MenuItem::Timed,
+    MenuItem::Limited,
+    MenuItem::Endless,
+    MenuItem::Mystery,
+    MenuItem::Orders,
+    MenuItem::Editor,
+];
+
+// Time spent looking for an endless board of the right length before
+// settling for the closest one
+const ENDLESS_BOARD_BUDGET: Duration = Duration::from_millis(1500);
+
+// How many pours past par a move limited board allows
+fn move_slack(par: usize) -> usize {
+    par / 4 + 2
+}
+
+// Fresh time given to every board in a timed run
+fn time_budget(difficulty: SimpleDifficulties) -> Duration {
+    match difficulty {
+        SimpleDifficulties::Easy => Duration::from_secs(45),
+        SimpleDifficulties::Medium => Duration::from_secs(120),
+        SimpleDifficulties::Hard => Duration::from_secs(180),
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct App {
     pub current_screen: CurrentScreen,
+    // Where to go back to when leaving the statistics
+    // or high score screens
+    pub previous_screen: CurrentScreen,
+    pub mode: GameMode,
+    // Index into MENU_ITEMS
+    pub menu_selection: usize,
+    pub difficulty: SimpleDifficulties,
+    pub seed: u64,
     pub cups: Vec<Cup>,
+    // What counts as finishing the board
+    pub goal: Box<dyn WinCondition>,
+    // Which pours are allowed
+    pub rule: Box<dyn PourRule>,
+    // Boards before each pour, oldest first, for undo and restart
+    pub history: Vec<Vec<Cup>>,
     pub src_selection: Option<u32>,
     pub tooltip: Option<String>,
+    pub moves: usize,
+    // Length of the optimal solution for the starting board,
+    // None when the solver could not find one
+    pub par: Option<usize>,
+    pub hints_used: usize,
+    pub started_at: Option<Instant>,
+    pub time_taken: Option<Duration>,
+    // Time allowed for this board in a timed run
+    pub time_limit: Option<Duration>,
+    // Boards finished so far in a timed run
+    pub boards_cleared: usize,
+    // Most pours allowed for this board
+    pub move_limit: Option<usize>,
+    // Points so far in an endless run
+    pub score: usize,
+    // Customers and their orders in order mode
+    pub shift: Option<Shift>,
+    pub units_poured: usize,
+    // Loaded when the statistics screen is opened, None if the
+    // stats file could not be read
+    pub stats: Option<Stats>,
+    // Loaded when a board is finished or the high score screen is opened
+    pub high_scores: Vec<HighScore>,
+    // Some while the player is typing their name for a new high score
+    pub name_entry: Option<String>,
+    // Printed on exit, a finished daily's spoiler free summary or the
+    // share code of a level from the editor
+    pub share: Option<String>,
+    // Some while a board is being built, and while it is played from
+    // the editor so it can be gone back to
+    pub editor: Option<Editor>,
+    // Some while the solver is playing the board
+    pub autoplay: Option<Autoplay>,
+    // Set once the solver has played a pour on this board
+    pub autoplayed: bool,
+    // Pours that got the board to where it is and how far into the
+    // game each was made, saved as a replay when the board ends
+    pub pours: Vec<(Duration, Pour)>,
+    // Some while a replay is being watched
+    pub playback: Option<Playback>,
+    // Set for games played headless, bots and scripts stay out of the
+    // player's stats, replays and high scores
+    pub scripted: bool,
 }
 
 impl App {
+    // Starts on the welcome screen
     pub fn new() -> App {
+        App {
+            current_screen: CurrentScreen::Welcome,
+            stats: load_stats().ok(),
+            ..Default::default()
+        }
+    }
+
+    pub fn classic(difficulty: SimpleDifficulties) -> App {
+        App::with_seed(difficulty, rand::random_range(0..1_000_000))
+    }
+
+    pub fn daily() -> App {
+        let day = today();
+        let mut app = App::with_seed(SimpleDifficulties::Hard, daily_seed(day));
+        app.mode = GameMode::Daily(day);
+        app
+    }
+
+    pub fn timed(difficulty: SimpleDifficulties, carried_over: Duration, boards_cleared: usize) -> App {
+        let mut app = App::classic(difficulty);
+        app.mode = GameMode::Timed;
+        app.time_limit = Some(time_budget(difficulty) + carried_over);
+        app.boards_cleared = boards_cleared;
+        app
+    }
+
+    pub fn limited(difficulty: SimpleDifficulties) -> App {
+        let mut app = App::classic(difficulty);
+        // A limit needs a par to be based on
+        for _ in 0..5 {
+            if app.par.is_some() {
+                break;
+            }
+            app = App::classic(difficulty);
+        }
+        app.mode = GameMode::Limited;
+        app.move_limit = app.par.map(|par| par + move_slack(par));
+        app
+    }
+
+    // depth is the number of boards already cleared in the run
+    pub fn endless(depth: usize, score: usize) -> App {
+        let params = BoardParams::endless(depth);
+        let seed = rand::random_range(0..1_000_000);
+        let (cups, par) = generate_board_with_par(
+            params,
+            BoardParams::endless_pars(depth),
+            ENDLESS_BOARD_BUDGET,
+            seed,
+        );
+        let mut app = App::with_par(params.difficulty(), seed, cups, par);
+        app.mode = GameMode::Endless;
+        app.boards_cleared = depth;
+        app.score = score;
+        app
+    }
+
+    // Hidden units don't pour with the run above them, so par is
+    // worked out again once the layers are hidden
+    pub fn mystery(difficulty: SimpleDifficulties) -> App {
+        let mut app = App::classic(difficulty);
+        app.mode = GameMode::Mystery;
+        app.cups = hide_layers(app.cups);
+        app.par = solve(&app.cups, app.goal.as_ref(), app.rule.as_ref()).map(|pours| pours.len());
+        app
+    }
+
+    pub fn from_level(level: Level) -> Result<App, String> {
+        let mut app = App::with_par(SimpleDifficulties::Hard, 0, level.cups, None);
+        app.mode = GameMode::Custom;
+        if let Some(name) = level.rules {
+            app = app.with_rule(rule_from_name(&name).ok_or(format!("Unknown rules '{}'", name))?);
+        }
+        if let Some(name) = level.goal {
+            let goal = goal_from_name(&name, &app.cups).ok_or(format!("Unknown goal '{}'", name))?;
+            app = app.with_goal(goal);
+        }
+        Ok(app.solved())
+    }
+
+    // Watches a saved game, the replay is checked against the level's
+    // rules before anything is shown
+    pub fn from_replay(replay: Replay) -> Result<App, String> {
+        let mut app = App::from_level(replay.level.clone())?;
+        app.seed = replay.seed;
+        app.started_at = None;
+        app.playback = Some(Playback::new(replay, app.rule.as_ref())?);
+        Ok(app)
+    }
+
+    // Shows the replay's board at its current position
+    pub fn show_playback(&mut self) {
+        if let Some(playback) = &self.playback {
+            self.cups = playback.board().to_vec();
+            self.moves = playback.position;
+        }
+    }
+
+    // A small board to start building from
+    pub fn editor() -> App {
+        let cups = generate_cups(SimpleDifficulties::Easy);
+        let editor = Editor {
+            saved: cups.clone(),
+            ..Default::default()
+        };
+        App::editing(cups, editor)
+    }
+
+    fn editing(cups: Vec<Cup>, editor: Editor) -> App {
+        App {
+            current_screen: CurrentScreen::Editor,
+            previous_screen: CurrentScreen::Editor,
+            mode: GameMode::Custom,
+            cups,
+            editor: Some(editor),
+            ..Default::default()
+        }
+    }
+
+    pub fn orders() -> App {
+        let seed = rand::random_range(0..1_000_000);
+        App {
+            current_screen: CurrentScreen::Main,
+            previous_screen: CurrentScreen::Main,
+            mode: GameMode::Orders,
+            seed,
+            cups: order_board(),
+            rule: Box::new(Sandbox),
+            started_at: Some(Instant::now()),
+            shift: Some(Shift::new(seed, Instant::now())),
+            tooltip: Some("Pour from the taps into a serving cup, then pick it and serve".to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn start(&self, item: MenuItem) -> App {
+        match item {
+            MenuItem::Classic => App::classic(self.difficulty),
+            MenuItem::Daily => App::daily(),
+            MenuItem::Timed => App::timed(self.difficulty, Duration::ZERO, 0),
+            MenuItem::Limited => App::limited(self.difficulty),
+            MenuItem::Endless => App::endless(0, 0),
+            MenuItem::Mystery => App::mystery(self.difficulty),
+            MenuItem::Orders => App::orders(),
+            MenuItem::Editor => App::editor(),
+        }
+    }
+
+    // The board to move on to after this one is finished
+    pub fn next_game(&self) -> App {
+        let mut next = match self.mode {
+            // The next board is played with the same goal and rules
+            GameMode::Classic => {
+                let mut next = App::classic(self.difficulty);
+                let custom = self.rule.name() != Standard.name()
+                    || self.goal.name() != UniqueMonochrome.name();
+                if self.rule.name() != Standard.name() {
+                    next = next.with_rule(rule_from_name(self.rule.name()).unwrap_or_default());
+                }
+                if let Some(goal) = goal_from_name(self.goal.name(), &next.cups) {
+                    next = next.with_goal(goal);
+                }
+                match custom {
+                    true => next.solved(),
+                    false => next,
+                }
+            }
+            GameMode::Timed => App::timed(
+                self.difficulty,
+                self.time_left().unwrap_or_default(),
+                self.boards_cleared + 1,
+            ),
+            GameMode::Limited => App::limited(self.difficulty),
+            GameMode::Endless => App::endless(self.boards_cleared + 1, self.score),
+            GameMode::Mystery => App::mystery(self.difficulty),
+            GameMode::Orders => App::orders(),
+            GameMode::Custom if self.editor.is_some() => self.back_to_editor(),
+            GameMode::Daily(_) | GameMode::Custom => App::new(),
+        };
+        next.share = self.share.clone();
+        next.scripted = self.scripted;
+        next
+    }
+
+    pub fn with_seed(difficulty: SimpleDifficulties, seed: u64) -> App {
+        App::with_cups(difficulty, seed, scramble_cups(generate_cups(difficulty), seed))
+    }
+
+    pub fn with_cups(difficulty: SimpleDifficulties, seed: u64, cups: Vec<Cup>) -> App {
+        let par = solve(&cups, &UniqueMonochrome, &Standard).map(|pours| pours.len());
+        App::with_par(difficulty, seed, cups, par)
+    }
+
+    // For boards whose par is already known from generating them. Every
+    // mode plays towards sorting the colors, other goals only come from
+    // --goal on a Classic board or from a level file.
+    fn with_par(difficulty: SimpleDifficulties, seed: u64, cups: Vec<Cup>, par: Option<usize>) -> App {
         App {
             current_screen: CurrentScreen::Main,
-            cups: scramble_cups(generate_cups(SimpleDifficulties::Hard)),
+            previous_screen: CurrentScreen::Main,
+            mode: GameMode::Classic,
+            menu_selection: 0,
+            difficulty,
+            seed,
+            cups,
+            goal: Box::default(),
+            rule: Box::default(),
+            history: vec![],
             src_selection: None,
             tooltip: None,
+            moves: 0,
+            par,
+            hints_used: 0,
+            started_at: Some(Instant::now()),
+            time_taken: None,
+            time_limit: None,
+            boards_cleared: 0,
+            move_limit: None,
+            score: 0,
+            shift: None,
+            units_poured: 0,
+            stats: None,
+            high_scores: vec![],
+            name_entry: None,
+            share: None,
+            editor: None,
+            autoplay: None,
+            autoplayed: false,
+            pours: vec![],
+            playback: None,
+            scripted: false,
+        }
+    }
+
+    // Swaps in a different goal, par is left for `solved` to work out
+    pub fn with_goal(mut self, goal: Box<dyn WinCondition>) -> App {
+        self.goal = goal;
+        self
+    }
+
+    // Swaps in different pour rules, par is left for `solved` too
+    pub fn with_rule(mut self, rule: Box<dyn PourRule>) -> App {
+        self.rule = rule;
+        self
+    }
+
+    // Works out par once the goal and rules are settled, so a board is
+    // only solved once however many of them were changed
+    pub fn solved(mut self) -> App {
+        self.par = solve(&self.cups, self.goal.as_ref(), self.rule.as_ref()).map(|pours| pours.len());
+        self
+    }
+
+    pub fn rating(&self) -> Rating {
+        rate(&self.cups, self.goal.as_ref(), self.rule.as_ref())
+    }
+
+    pub fn level(&self) -> Level {
+        Level {
+            cups: self.cups.clone(),
+            ..Default::default()
+        }
+    }
+
+    // Runs the solver over the board being built and rates it
+    pub fn check_level(&mut self) {
+        let rating = self.rating();
+        self.par = rating.par;
+        self.tooltip = Some(match self.par {
+            Some(0) => "That board is already sorted".to_string(),
+            Some(_) => format!("Difficulty {}", rating),
+            None => "The solver could not find a way to sort this board".to_string(),
+        });
+    }
+
+    pub fn save_level(&mut self) {
+        self.tooltip = Some(match save_level(&self.level()) {
+            Ok(path) => {
+                if let Some(editor) = self.editor.as_mut() {
+                    editor.saved = self.cups.clone();
+                }
+                format!("Saved to {}", path.display())
+            }
+            Err(e) => e,
+        });
+    }
+
+    // The code is printed again on exit so it can be copied
+    pub fn share_level(&mut self) {
+        let code = share_code(&self.level());
+        self.tooltip = Some(format!("Share code (printed when you quit): {}", code));
+        self.share = Some(format!("Play this level with: terminal_barista --code {}", code));
+    }
+
+    pub fn play_level(&self) -> Result<App, String> {
+        let mut app = App::from_level(self.level())?;
+        app.editor = self.editor.clone();
+        app.share = self.share.clone();
+        Ok(app)
+    }
+
+    // Back to building the board as it was before it was played
+    fn back_to_editor(&self) -> App {
+        let start = self.history.first().unwrap_or(&self.cups).clone();
+        App::editing(start, self.editor.clone().unwrap_or_default())
+    }
+
+    // Pours are only counted as moves when they succeed
+    pub fn pour(&mut self, src: usize, dst: usize) -> Result<(), String> {
+        if src >= self.cups.len() || dst >= self.cups.len() {
+            return Err("There is no cup there".to_string());
+        }
+        if self.mode == GameMode::Orders {
+            return self.pour_order(src, dst);
+        }
+        let (new_src, new_dst) = self.rule.pour(&self.cups[src], &self.cups[dst])?;
+        self.history.push(self.cups.clone());
+        let at = self.started_at.map(|t| t.elapsed()).unwrap_or_default();
+        self.pours.push((at, (src, dst)));
+        // Counted from the source, a shot leaves the destination with less
+        self.units_poured += self.cups[src].liquids.len() - new_src.liquids.len();
+        self.cups[src] = new_src;
+        self.cups[dst] = new_dst;
+        settle(&mut self.cups);
+        self.moves += 1;
+        if self.goal.is_won(&self.cups) {
+            self.finish();
+        } else if self.moves_left() == Some(0) {
+            self.src_selection = None;
+            self.current_screen = CurrentScreen::GameOver;
+        } else if legal_pours(&self.cups, self.rule.as_ref()).is_empty() {
+            self.src_selection = None;
+            if self.mode == GameMode::Endless {
+                self.tooltip = Some("No pours left, that's the end of the run".to_string());
+                self.end_run();
+            } else {
+                self.current_screen = CurrentScreen::Stuck;
+            }
+        }
+        Ok(())
+    }
+
+    // Drinks are built one unit at a time and layers don't need to
+    // match. Taps refill straight away and can't be poured into.
+    fn pour_order(&mut self, src: usize, dst: usize) -> Result<(), String> {
+        if is_tap(dst) {
+            return Err("Taps can only be poured from".to_string());
+        }
+        let (mut new_src, new_dst) = self.rule.pour(&self.cups[src], &self.cups[dst])?;
+        if is_tap(src) {
+            new_src = self.cups[src].clone();
+        }
+        self.history.push(self.cups.clone());
+        self.units_poured += 1;
+        self.cups[src] = new_src;
+        self.cups[dst] = new_dst;
+        self.moves += 1;
+        Ok(())
+    }
+
+    // Hands the selected serving cup to a customer
+    pub fn serve(&mut self) -> Result<String, String> {
+        let cup_idx = self.take_serving_selection()?;
+        let shift = self.shift.as_mut().ok_or("There are no customers to serve")?;
+        let served = shift.serve(&self.cups[cup_idx].liquids, Instant::now())?;
+        self.cups[cup_idx].liquids.clear();
+        // A served drink can't be taken back
+        self.history.clear();
+        if shift.is_over() {
+            self.end_shift();
+        }
+        Ok(format!(
+            "Customer {}: {}% right, +{} points",
+            served.customer,
+            (served.accuracy * 100.0).round(),
+            served.points
+        ))
+    }
+
+    // Tips the selected serving cup down the sink
+    pub fn dump(&mut self) -> Result<(), String> {
+        let cup_idx = self.take_serving_selection()?;
+        self.history.push(self.cups.clone());
+        self.cups[cup_idx].liquids.clear();
+        Ok(())
+    }
+
+    fn take_serving_selection(&mut self) -> Result<usize, String> {
+        let cup_idx = self.src_selection.take().ok_or("Pick a serving cup first")? as usize;
+        if cup_idx >= self.cups.len() || is_tap(cup_idx) {
+            return Err("Pick a serving cup first".to_string());
+        }
+        Ok(cup_idx)
+    }
+
+    fn end_shift(&mut self) {
+        self.time_taken = self.started_at.map(|t| t.elapsed());
+        self.src_selection = None;
+        self.current_screen = CurrentScreen::Finished;
+    }
+
+    // Ends an endless run, by giving up or getting stuck
+    pub fn end_run(&mut self) {
+        if self.current_screen == CurrentScreen::Finished {
+            self.boards_cleared += 1;
+        }
+        self.abandon();
+        self.time_taken = self.started_at.map(|t| t.elapsed());
+        self.src_selection = None;
+        self.current_screen = CurrentScreen::GameOver;
+        if !self.scripted {
+            let _ = record_endless(&EndlessRecord {
+                depth: self.boards_cleared,
+                score: self.score,
+            });
+        }
+    }
+
+    // Points for clearing an endless board, more colors are worth more
+    // and finishing close to par earns a bonus
+    pub fn board_points(&self) -> usize {
+        let colors = BoardParams::endless(self.boards_cleared).colors;
+        let bonus = match self.par {
+            Some(par) => (par + par / 2).saturating_sub(self.moves) * 10,
+            None => 0,
+        };
+        colors * 50 + bonus
+    }
+
+    pub fn undo(&mut self) -> Result<(), String> {
+        let previous = self.history.pop().ok_or("Nothing to undo")?;
+        self.cups = previous;
+        self.pours.pop();
+        self.moves -= 1;
+        self.src_selection = None;
+        self.current_screen = CurrentScreen::Main;
+        Ok(())
+    }
+
+    // Back to the starting board, the clock keeps running
+    pub fn restart(&mut self) {
+        if let Some(first) = self.history.first() {
+            self.cups = first.clone();
+        }
+        self.history.clear();
+        self.pours.clear();
+        self.moves = 0;
+        self.src_selection = None;
+        self.current_screen = CurrentScreen::Main;
+    }
+
+    // The solver's way on from here. On a mystery board it only looks
+    // as far as the next hidden layer, anything further would give away
+    // what's under it.
+    fn solution(&self) -> Option<Vec<Pour>> {
+        let hidden = self.cups.iter().any(|c| c.hidden > 0);
+        if hidden {
+            next_reveal(&self.cups, self.goal.as_ref(), self.rule.as_ref())
+        } else {
+            solve(&self.cups, self.goal.as_ref(), self.rule.as_ref())
+        }
+    }
+
+    // Hands the board over to the solver, which plays it one step at a
+    // time from tick
+    pub fn start_autoplay(&mut self) {
+        self.src_selection = None;
+        if self.mode == GameMode::Orders {
+            self.tooltip = Some("The solver doesn't work behind the counter".to_string());
+            return;
+        }
+        match self.solution() {
+            Some(plan) if !plan.is_empty() => {
+                self.tooltip = None;
+                self.autoplay = Some(Autoplay::new(plan));
+            }
+            _ => self.tooltip = Some(self.no_solution()),
+        }
+    }
+
+    fn no_solution(&self) -> String {
+        match self.rule.is_wide_open() {
+            true => "The solver can't plan ahead with these rules".to_string(),
+            false => "No solution from here :(".to_string(),
+        }
+    }
+
+    // The player takes over from the solver
+    pub fn stop_autoplay(&mut self) {
+        self.autoplay = None;
+        self.src_selection = None;
+    }
+
+    // Picks up the cups for the solver's next pour, or pours them. Each
+    // pour the solver makes counts as a hint.
+    pub fn step_autoplay(&mut self) {
+        let Some(autoplay) = self.autoplay.as_mut() else {
+            return;
+        };
+        autoplay.last_step = Instant::now();
+        if let Some((src, dst)) = autoplay.pending.take() {
+            self.src_selection = None;
+            self.hints_used += 1;
+            self.autoplayed = true;
+            if let Err(e) = self.pour(src, dst) {
+                self.tooltip = Some(e);
+                self.autoplay = None;
+            } else if self.current_screen != CurrentScreen::Main {
+                self.autoplay = None;
+            }
+            return;
+        }
+        let mut plan = std::mem::take(&mut autoplay.plan);
+        // A mystery plan stops at the next reveal, so plan again
+        if plan.is_empty() {
+            plan = self.solution().unwrap_or_default();
+        }
+        if plan.is_empty() {
+            self.tooltip = Some("No solution from here :(".to_string());
+            self.autoplay = None;
+            return;
+        }
+        let pour = plan.remove(0);
+        self.src_selection = Some(pour.0 as u32);
+        if let Some(autoplay) = self.autoplay.as_mut() {
+            autoplay.plan = plan;
+            autoplay.pending = Some(pour);
+        }
+    }
+
+    // Shows the next pour in the tooltip and hands it back too
+    pub fn hint(&mut self) -> Option<Pour> {
+        self.src_selection = None;
+        if self.mode == GameMode::Orders {
+            self.tooltip = Some("No hints behind the counter, follow the orders".to_string());
+            return None;
+        }
+        let pour = self.solution().and_then(|pours| pours.first().copied());
+        match pour {
+            Some((src, dst)) => {
+                self.hints_used += 1;
+                self.tooltip = Some(format!("Hint: pour {} into {}", src + 1, dst + 1));
+            }
+            None => self.tooltip = Some(self.no_solution()),
+        }
+        pour
+    }
+
+    // Asks the solver whether the board can still be finished, which
+    // costs a star like a hint does
+    pub fn check_dead_end(&mut self) {
+        self.src_selection = None;
+        if self.mode == GameMode::Orders {
+            self.tooltip = Some("The taps never run dry, there are no dead ends here".to_string());
+            return;
+        }
+        // The solver would be looking under the hidden layers
+        if self.cups.iter().any(|c| c.hidden > 0) {
+            self.tooltip = Some("Can't tell while layers are still hidden".to_string());
+            return;
+        }
+        match is_dead_end(&self.cups, self.goal.as_ref(), self.rule.as_ref()) {
+            // Like running out of pours, a dead end finishes an endless run
+            Some(true) if self.mode == GameMode::Endless => {
+                self.hints_used += 1;
+                self.tooltip = Some("No way through from here, that's the end of the run".to_string());
+                self.end_run();
+            }
+            Some(true) => {
+                self.hints_used += 1;
+                self.current_screen = CurrentScreen::Stuck;
+            }
+            Some(false) => {
+                self.hints_used += 1;
+                self.tooltip = Some("This board can still be finished".to_string());
+            }
+            None => self.tooltip = Some("Too many ways to go from here to tell".to_string()),
+        }
+    }
+
+    // Leaving the game, quitting part way through a board counts as a loss
+    pub fn quit(&mut self) {
+        if self.mode == GameMode::Endless && self.current_screen != CurrentScreen::GameOver {
+            self.end_run();
+        } else {
+            self.abandon();
+        }
+    }
+
+    // Records the board as lost if it was left part way through
+    pub fn abandon(&self) {
+        // Order shifts are not boards, so they don't count towards
+        // stats, and watching a replay isn't playing
+        if self.mode != GameMode::Orders
+            && self.playback.is_none()
+            && self.time_taken.is_none()
+            && self.moves > 0
+        {
+            self.record(false);
+        }
+    }
+
+    // Every board that ends goes into the stats and is saved as a replay
+    fn record(&self, won: bool) {
+        if self.scripted {
+            return;
+        }
+        // Level files, codes and editor boards have no difficulty of
+        // their own, so they would only skew the stats for Hard
+        if self.mode != GameMode::Custom {
+            let _ = record_game(&self.game_record(won));
+        }
+        let _ = save_replay(&self.replay(won));
+    }
+
+    // The solver sorting the board from where it is, a pour a second
+    pub fn solution_replay(&self) -> Result<Replay, String> {
+        let solution = solve(&self.cups, self.goal.as_ref(), self.rule.as_ref())
+            .ok_or("The solver could not find a way to sort this board")?;
+        let mut replay = self.replay(true);
+        replay.level.cups = self.cups.clone();
+        replay.pours = solution
+            .into_iter()
+            .enumerate()
+            .map(|(i, pour)| (Duration::from_secs(i as u64 + 1), pour))
+            .collect();
+        Ok(replay)
+    }
+
+    pub fn replay(&self, won: bool) -> Replay {
+        let start = self.history.first().unwrap_or(&self.cups).clone();
+        // Names are only written down when they aren't the usual ones
+        let goal = self.goal.name();
+        let rule = self.rule.name();
+        Replay {
+            seed: self.seed,
+            won,
+            level: Level {
+                cups: start,
+                goal: (goal != UniqueMonochrome.name()).then(|| goal.to_string()),
+                rules: (rule != Standard.name()).then(|| rule.to_string()),
+            },
+            pours: self.pours.clone(),
+        }
+    }
+
+    // None when the board has no move limit
+    pub fn moves_left(&self) -> Option<usize> {
+        Some(self.move_limit?.saturating_sub(self.moves))
+    }
+
+    pub fn finish(&mut self) {
+        self.time_taken = self.started_at.map(|t| t.elapsed());
+        self.current_screen = CurrentScreen::Finished;
+        // Anything still hidden is shown once the board is done
+        for cup in self.cups.iter_mut() {
+            cup.hidden = 0;
+        }
+        self.record(true);
+        if self.mode == GameMode::Custom {
+            // Level files have no seed to rank them by
+            return;
+        }
+        if self.mode == GameMode::Endless {
+            self.score += self.board_points();
+            // Endless boards are not built from their seed alone, so
+            // they have no place on the high score tables
+            return;
+        }
+        // Other goals, rules and hidden layers play the same board
+        // differently, so their scores would not compare with the rest
+        // of the table. Nor would a board the solver or a bot played.
+        if self.goal.name() != UniqueMonochrome.name()
+            || self.rule.name() != Standard.name()
+            || self.mode == GameMode::Mystery
+            || self.autoplayed
+            || self.scripted
+        {
+            return;
+        }
+        if let GameMode::Daily(day) = self.mode {
+            let _ = record_daily(&DailyRecord {
+                day,
+                moves: self.moves,
+                par: self.par,
+                time_taken: self.time_taken.unwrap_or_default(),
+            });
+            self.share = Some(share_text(
+                day,
+                self.moves,
+                self.par,
+                self.hints_used,
+                self.stars(),
+            ));
+        }
+        self.high_scores = load_scores().unwrap_or_default();
+        if makes_leaderboard(&self.high_scores, &self.high_score("")) {
+            let name = std::env::var("USER").unwrap_or_default();
+            self.name_entry = Some(name.chars().take(MAX_NAME_LENGTH).collect());
         }
     }
+
+    // None when the board has no time limit
+    pub fn time_left(&self) -> Option<Duration> {
+        let limit = self.time_limit?;
+        let elapsed = self.time_taken.or(self.started_at.map(|t| t.elapsed()))?;
+        Some(limit.saturating_sub(elapsed))
+    }
+
+    // Called regularly by the event loop, even when no keys are pressed
+    pub fn tick(&mut self) {
+        // A board still being played has no time_taken yet
+        if self.time_taken.is_none() && self.time_left() == Some(Duration::ZERO) {
+            self.time_taken = self.time_limit;
+            self.src_selection = None;
+            self.name_entry = None;
+            self.current_screen = CurrentScreen::GameOver;
+            self.record(false);
+        }
+        if let Some(playback) = self.playback.as_mut()
+            && playback.until_next_step() == Some(Duration::ZERO)
+        {
+            playback.seek(playback.position + 1);
+            self.show_playback();
+        }
+        // The solver waits while an overlay is open
+        if self.current_screen == CurrentScreen::Main
+            && let Some(autoplay) = &self.autoplay
+            && autoplay.until_next_step() == Some(Duration::ZERO)
+        {
+            self.step_autoplay();
+        }
+        if let Some(shift) = self.shift.as_mut()
+            && self.current_screen == CurrentScreen::Main
+        {
+            let walkouts = shift.tick(Instant::now());
+            if walkouts > 0 {
+                self.tooltip = Some("A customer got tired of waiting and left 😤".to_string());
+            }
+            if shift.is_over() {
+                self.end_shift();
+            }
+        }
+    }
+
+    pub fn high_score(&self, name: &str) -> HighScore {
+        HighScore {
+            difficulty: self.difficulty,
+            seed: self.seed,
+            moves: self.moves,
+            time_taken: self.time_taken.unwrap_or_default(),
+            name: name.to_string(),
+        }
+    }
+
+    pub fn submit_name(&mut self) {
+        let Some(name) = self.name_entry.take() else {
+            return;
+        };
+        let name = match name.trim() {
+            "" => "anonymous",
+            trimmed => trimmed,
+        };
+        let score = self.high_score(name);
+        match save_score(&score) {
+            Ok(()) => self.open_overlay(CurrentScreen::HighScores),
+            Err(e) => self.tooltip = Some(format!("Could not save high score: {}", e)),
+        }
+    }
+
+    pub fn game_record(&self, won: bool) -> GameRecord {
+        GameRecord {
+            difficulty: self.difficulty,
+            won,
+            moves: self.moves,
+            time_taken: self
+                .time_taken
+                .or(self.started_at.map(|t| t.elapsed()))
+                .unwrap_or_default(),
+            units_poured: self.units_poured,
+        }
+    }
+
+    // Shows the statistics or high score screen on top of the game
+    pub fn open_overlay(&mut self, screen: CurrentScreen) {
+        match screen {
+            CurrentScreen::Statistics => self.stats = load_stats().ok(),
+            CurrentScreen::HighScores => self.high_scores = load_scores().unwrap_or_default(),
+            _ => {}
+        }
+        self.previous_screen = std::mem::replace(&mut self.current_screen, screen);
+    }
+
+    pub fn close_overlay(&mut self) {
+        self.current_screen = std::mem::take(&mut self.previous_screen);
+    }
+
+    // 3 stars for matching par, 2 for staying within half again of par,
+    // and 1 otherwise. Using a hint costs a star.
+    pub fn stars(&self) -> Option<usize> {
+        let par = self.par?;
+        let mut stars = if self.moves <= par {
+            3
+        } else if self.moves <= par + par / 2 {
+            2
+        } else {
+            1
+        };
+        if self.hints_used > 0 && stars > 1 {
+            stars -= 1;
+        }
+        Some(stars)
+    }
+}
+
+#[cfg(test)]
+mod test_app {
+    use super::*;
+    use crate::cup::cup;
+    use crate::liquids::LiquidColors;
+    use crate::orders::TAPS;
+
+    fn limited_app(move_limit: usize) -> App {
+        App {
+            current_screen: CurrentScreen::Main,
+            cups: vec![
+                cup(4, vec![LiquidColors::Red, LiquidColors::Green]),
+                cup(4, vec![LiquidColors::Green, LiquidColors::Red]),
+                cup(4, vec![]),
+            ],
+            move_limit: Some(move_limit),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn failed_pours_are_not_moves() {
+        let mut app = limited_app(3);
+        assert!(app.pour(0, 1).is_err());
+        assert!(app.pour(0, 7).is_err());
+        assert_eq!(app.moves, 0);
+        assert_eq!(app.moves_left(), Some(3));
+    }
+
+    #[test]
+    fn running_out_of_moves_and_undo() {
+        let mut app = limited_app(1);
+        app.pour(0, 2).unwrap();
+        assert_eq!(app.current_screen, CurrentScreen::GameOver);
+        assert_eq!(app.moves_left(), Some(0));
+
+        app.undo().unwrap();
+        assert_eq!(app.current_screen, CurrentScreen::Main);
+        assert_eq!(app.moves, 0);
+        assert_eq!(app.cups[2].liquids, vec![]);
+        assert!(app.undo().is_err());
+    }
+
+    #[test]
+    fn restart_goes_back_to_the_first_board() {
+        let mut app = limited_app(5);
+        let start = app.cups.clone();
+        app.pour(0, 2).unwrap();
+        app.pour(1, 0).unwrap();
+        app.restart();
+        assert_eq!(app.moves, 0);
+        assert_eq!(
+            app.cups.iter().map(|c| c.liquids.clone()).collect::<Vec<_>>(),
+            start.iter().map(|c| c.liquids.clone()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn shots_count_as_one_unit_poured() {
+        let mut app = limited_app(5);
+        app.cups[0].liquids.push(LiquidColors::Shot);
+        app.pour(0, 1).unwrap();
+        assert_eq!(app.units_poured, 1);
+        assert!(app.cups[1].liquids.is_empty());
+        assert_eq!(app.cups[0].liquids, vec![LiquidColors::Red, LiquidColors::Green]);
+    }
+
+    #[test]
+    fn mystery_par_is_for_the_hidden_board() {
+        let app = App::mystery(SimpleDifficulties::Easy);
+        let shown: Vec<Cup> = app.cups.iter().map(|c| Cup { hidden: 0, ..c.clone() }).collect();
+        let shown_par = solve(&shown, &UniqueMonochrome, &Standard).map(|p| p.len());
+        // Taking the hidden units one run at a time never makes it shorter
+        assert!(app.par >= shown_par);
+        assert_eq!(
+            app.par,
+            solve(&app.cups, &UniqueMonochrome, &Standard).map(|p| p.len())
+        );
+    }
+
+    #[test]
+    fn stuck_until_a_pour_is_undone() {
+        let mut app = App {
+            current_screen: CurrentScreen::Main,
+            cups: vec![
+                cup(4, vec![LiquidColors::Red, LiquidColors::Green, LiquidColors::Red]),
+                cup(
+                    4,
+                    vec![
+                        LiquidColors::Green,
+                        LiquidColors::Red,
+                        LiquidColors::Green,
+                        LiquidColors::Red,
+                    ],
+                ),
+            ],
+            ..Default::default()
+        };
+        // One pour is left, but it can't lead anywhere
+        app.check_dead_end();
+        assert_eq!(app.current_screen, CurrentScreen::Stuck);
+        assert_eq!(app.hints_used, 1);
+
+        app.current_screen = CurrentScreen::Main;
+        app.pour(1, 0).unwrap();
+        assert_eq!(app.current_screen, CurrentScreen::Stuck);
+        app.undo().unwrap();
+        assert_eq!(app.current_screen, CurrentScreen::Main);
+
+        // A dead end finishes an endless run for good
+        app.mode = GameMode::Endless;
+        app.scripted = true;
+        app.check_dead_end();
+        assert_eq!(app.current_screen, CurrentScreen::GameOver);
+    }
+
+    #[test]
+    fn autoplay_picks_up_then_pours() {
+        let mut app = limited_app(5);
+        app.start_autoplay();
+        app.step_autoplay();
+        let (src, dst) = app.autoplay.as_ref().unwrap().pending.unwrap();
+        assert_eq!(app.src_selection, Some(src as u32));
+        assert_eq!(app.moves, 0);
+        app.step_autoplay();
+        assert_eq!(app.moves, 1);
+        assert_eq!(app.src_selection, None);
+        assert_eq!(app.hints_used, 1);
+        assert!(!app.cups[dst].liquids.is_empty());
+
+        // Taking over leaves the board where the solver got to
+        app.step_autoplay();
+        app.stop_autoplay();
+        app.step_autoplay();
+        assert_eq!(app.moves, 1);
+        assert_eq!(app.src_selection, None);
+    }
+
+    #[test]
+    fn taps_refill_and_drinks_are_served() {
+        let mut app = App::orders();
+        let tap = app.cups[0].clone();
+        let serving = TAPS.len();
+        assert!(app.pour(serving, 0).is_err());
+        app.pour(0, serving).unwrap();
+        app.pour(1, serving).unwrap();
+        assert_eq!(app.cups[0].liquids, tap.liquids);
+        assert_eq!(app.cups[serving].liquids.len(), 2);
+
+        // Only serving cups can be handed over
+        app.src_selection = Some(0);
+        assert!(app.serve().is_err());
+        app.src_selection = Some(serving as u32);
+        app.serve().unwrap();
+        assert!(app.cups[serving].liquids.is_empty());
+        assert_eq!(app.shift.as_ref().unwrap().served.len(), 1);
+    }
 }
@@ -0,0 +1,45 @@
+use std::time::{Duration, Instant};
+
+use crate::solver::Pour;
+use crate::speed::Speed;
+
+// Time between steps at 1x. Every pour takes two steps, one to pick
+// up the cups and one to pour.
+const STEP: Duration = Duration::from_millis(400);
+
+// The solver playing the board on its own, one step at a time
+#[derive(Debug, Clone)]
+pub struct Autoplay {
+    // Pours still to come, the next one first
+    pub plan: Vec<Pour>,
+    // Picked up and waiting to be poured
+    pub pending: Option<Pour>,
+    pub speed: Speed,
+    pub paused: bool,
+    pub last_step: Instant,
+}
+
+impl Autoplay {
+    pub fn new(plan: Vec<Pour>) -> Autoplay {
+        Autoplay {
+            plan,
+            pending: None,
+            speed: Speed::default(),
+            paused: false,
+            last_step: Instant::now(),
+        }
+    }
+
+    // How long until the next step, None while paused
+    pub fn until_next_step(&self) -> Option<Duration> {
+        match self.paused {
+            true => None,
+            false => Some(self.speed.scale(STEP).saturating_sub(self.last_step.elapsed())),
+        }
+    }
+
+    pub fn status(&self) -> String {
+        let state = if self.paused { "paused" } else { "playing" };
+        format!("Solver {} at {}", state, self.speed.label())
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use ratatui::{
+    Terminal,
+    backend::TestBackend,
+    buffer::{Buffer, Cell},
+    style::Modifier,
+};
+
+use crate::app::App;
+use crate::headless::json_string;
+use crate::render::color_code;
+use crate::replay::Replay;
+use crate::ui::ui;
+
+// Size of the terminal the game is drawn into
+const CAST_WIDTH: u16 = 100;
+const CAST_HEIGHT: u16 = 30;
+// How long the finished board stays up at the end
+const END_HOLD: Duration = Duration::from_secs(2);
+// Long stops to think are cut short by the player
+const IDLE_LIMIT: u64 = 3;
+
+fn style_codes(cell: &Cell) -> String {
+    let mut codes = vec![
+        "0".to_string(),
+        color_code(cell.fg, false),
+        color_code(cell.bg, true),
+    ];
+    for (modifier, code) in [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+    ] {
+        if cell.modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+// A whole screen as ANSI, starting from a cleared terminal. Blank cells
+// are skipped over, and so are the cells wide characters spill into.
+fn frame_to_ansi(buffer: &Buffer) -> String {
+    let mut ansi = String::from("\x1b[0m\x1b[2J");
+    let mut cursor = None;
+    let mut style = String::new();
+    for (x, y, cell) in Buffer::empty(buffer.area).diff(buffer) {
+        if cursor != Some((x, y)) {
+            ansi.push_str(&format!("\x1b[{};{}H", y + 1, x + 1));
+        }
+        let cell_style = style_codes(cell);
+        if cell_style != style {
+            ansi.push_str(&cell_style);
+            style = cell_style;
+        }
+        ansi.push_str(cell.symbol());
+        cursor = Some((x + 1, y));
+    }
+    ansi.push_str("\x1b[0m");
+    ansi
+}
+
+fn draw(terminal: &mut Terminal<TestBackend>, app: &App) -> Result<String, String> {
+    terminal.draw(|f| ui(f, app)).map_err(|e| e.to_string())?;
+    Ok(frame_to_ansi(terminal.backend().buffer()))
+}
+
+// An asciinema v2 recording of a replay, one frame for the starting
+// board and one after each pour, at the times they were made
+pub fn cast_replay(replay: Replay, title: &str) -> Result<String, String> {
+    let mut app = App::from_replay(replay)?;
+    // The board is drawn like one being played, without the replay's
+    // controls along the bottom
+    let playback = app.playback.take().ok_or("Nothing to replay")?;
+    let mut terminal =
+        Terminal::new(TestBackend::new(CAST_WIDTH, CAST_HEIGHT)).map_err(|e| e.to_string())?;
+    let mut lines = vec![format!(
+        "{{\"version\":2,\"width\":{},\"height\":{},\"idle_time_limit\":{},\"title\":{}}}",
+        CAST_WIDTH,
+        CAST_HEIGHT,
+        IDLE_LIMIT,
+        json_string(title)
+    )];
+    let mut at = Duration::ZERO;
+    for (position, board) in playback.boards.iter().enumerate() {
+        if position > 0 {
+            at = at.max(playback.replay.pours[position - 1].0);
+        }
+        app.cups = board.clone();
+        app.moves = position;
+        let frame = draw(&mut terminal, &app)?;
+        lines.push(format!(
+            "[{:.6},\"o\",{}]",
+            at.as_secs_f64(),
+            json_string(&frame)
+        ));
+    }
+    lines.push(format!("[{:.6},\"o\",\"\"]", (at + END_HOLD).as_secs_f64()));
+    Ok(lines.join("\n") + "\n")
+}
+
+#[cfg(test)]
+mod test_cast {
+    use super::*;
+    use crate::level::parse_level;
+
+    #[test]
+    fn casts_every_pour() {
+        let replay = Replay {
+            seed: 3,
+            won: true,
+            level: parse_level("4 red green\n4 green red\n4").unwrap(),
+            pours: vec![
+                (Duration::from_millis(500), (0, 2)),
+                (Duration::from_millis(1500), (1, 0)),
+            ],
+        };
+        let cast = cast_replay(replay, "two \"pours\"").unwrap();
+        let lines: Vec<&str> = cast.lines().collect();
+        assert_eq!(
+            lines[0],
+            "{\"version\":2,\"width\":100,\"height\":30,\"idle_time_limit\":3,\"title\":\"two \\\"pours\\\"\"}"
+        );
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("[0.000000,\"o\",\"\\u001b[0m\\u001b[2J"));
+        assert!(lines[2].starts_with("[0.500000,\"o\","));
+        // Blank cells are jumped over rather than written out
+        assert!(lines[2].contains("Moves:\\u001b["));
+        assert_ne!(lines[1][20..], lines[2][20..]);
+        assert_eq!(lines[4], "[3.500000,\"o\",\"\"]");
+    }
+}
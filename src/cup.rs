@@ -1,10 +1,66 @@
 use crate::liquids::LiquidColors;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::{collections::HashSet, fmt};
 
-#[derive(Debug, Clone)]
+// What it takes for a locked cup to open
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Lock {
+    // This many more pours
+    Pours(usize),
+    // Some other cup holding every unit of this color
+    Color(LiquidColors),
+}
+
+impl fmt::Display for Lock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lock::Pours(1) => write!(f, "1 more pour"),
+            Lock::Pours(n) => write!(f, "{} more pours", n),
+            Lock::Color(color) => write!(f, "{} is sorted", color.to_string().to_lowercase()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CupKind {
+    #[default]
+    Normal,
+    // Takes pours, but nothing can be poured back out of it
+    OneWay,
+    // Closed both ways until the lock opens
+    Locked(Lock),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Cup {
     pub capacity: usize,
     pub liquids: Vec<LiquidColors>,
+    // How many units at the bottom are still hidden in mystery mode.
+    // The top unit is always revealed.
+    pub hidden: usize,
+    pub kind: CupKind,
+}
+
+// A plain cup to build boards from in tests
+#[cfg(test)]
+pub fn cup(capacity: usize, liquids: Vec<LiquidColors>) -> Cup {
+    Cup {
+        capacity,
+        liquids,
+        hidden: 0,
+        kind: CupKind::Normal,
+    }
+}
+
+impl Cup {
+    pub fn is_revealed(&self, idx: usize) -> bool {
+        idx >= self.hidden
+    }
+
+    // Shows the unit that ended up on top after a pour
+    fn reveal_top(&mut self) {
+        self.hidden = self.hidden.min(self.liquids.len().saturating_sub(1));
+    }
 }
 
 impl fmt::Display for Cup {
@@ -19,10 +75,102 @@ impl fmt::Display for Cup {
     }
 }
 
+// Cup types, ice and shots behave the same whatever the pour rules
+// are. Returns None when neither cup is special.
+fn pour_special(a: &Cup, b: &Cup) -> Option<Result<(Cup, Cup), String>> {
+    for cup in [a, b] {
+        if let CupKind::Locked(lock) = &cup.kind {
+            return Some(Err(format!("That cup stays locked until {}", lock)));
+        }
+    }
+    if a.kind == CupKind::OneWay {
+        return Some(Err("Nothing can be poured out of a one-way cup".to_string()));
+    }
+    if let Some(LiquidColors::Ice(..)) = b.liquids.last() {
+        return Some(Err("Nothing can be poured onto ice until it melts".to_string()));
+    }
+    match a.liquids.last()? {
+        LiquidColors::Ice(..) => Some(Err("That ice hasn't melted yet".to_string())),
+        // A shot lands on top of a full cup just as well
+        LiquidColors::Shot if !b.liquids.is_empty() => {
+            let mut new_a = a.clone();
+            new_a.liquids.pop();
+            new_a.reveal_top();
+            let new_b = Cup {
+                capacity: b.capacity,
+                liquids: vec![],
+                hidden: 0,
+                kind: b.kind.clone(),
+            };
+            Some(Ok((new_a, new_b)))
+        }
+        _ => None,
+    }
+}
+
+// Runs after every pour, ice melts and locks open as the pours add up
+pub fn settle(cups: &mut [Cup]) {
+    melt_ice(cups);
+    open_locks(cups);
+}
+
+// Each move brings every ice block a move closer to melting
+fn melt_ice(cups: &mut [Cup]) {
+    for cup in cups.iter_mut() {
+        for liquid in cup.liquids.iter_mut() {
+            if let LiquidColors::Ice(color, moves) = liquid {
+                *liquid = match moves {
+                    0 | 1 => *color.clone(),
+                    n => LiquidColors::Ice(color.clone(), *n - 1),
+                };
+            }
+        }
+    }
+}
+
+// Every unit of the color is in one open cup with nothing else. Ice of
+// the color still has to melt first, wherever it is.
+fn is_color_sorted(cups: &[Cup], color: &LiquidColors) -> bool {
+    let total = cups
+        .iter()
+        .flat_map(|c| c.liquids.iter())
+        .filter(|l| match l {
+            LiquidColors::Ice(frozen, _) => **frozen == *color,
+            l => *l == color,
+        })
+        .count();
+    cups.iter().any(|c| {
+        !matches!(c.kind, CupKind::Locked(_))
+            && c.liquids.len() == total
+            && c.liquids.iter().all(|l| l == color)
+    })
+}
+
+fn open_locks(cups: &mut [Cup]) {
+    let opened: Vec<bool> = cups
+        .iter()
+        .map(|cup| match &cup.kind {
+            CupKind::Locked(Lock::Pours(pours)) => *pours <= 1,
+            CupKind::Locked(Lock::Color(color)) => is_color_sorted(cups, color),
+            _ => false,
+        })
+        .collect();
+    for (cup, opened) in cups.iter_mut().zip(opened) {
+        if opened {
+            cup.kind = CupKind::Normal;
+        } else if let CupKind::Locked(Lock::Pours(pours)) = &mut cup.kind {
+            *pours -= 1;
+        }
+    }
+}
+
 pub fn pour_a_into_b(a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
     if std::ptr::eq(a, b) {
         return Err("Cannot pour a cup into itself".to_string());
     }
+    if let Some(result) = pour_special(a, b) {
+        return result;
+    }
     if b.capacity == b.liquids.len() {
         return Err("Destination cup has no space x_x".to_string());
     }
@@ -31,30 +179,52 @@ pub fn pour_a_into_b(a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
     }
     let a_top = a.liquids.last().unwrap();
     let b_top = b.liquids.last();
-    if b_top.is_some() && b_top.unwrap() != a_top {
+    if b_top.is_some_and(|b_top| !a_top.matches(b_top)) {
         return Err("Source and destination colors do not match".to_string());
     }
+    if *a_top == LiquidColors::Shot {
+        return Err("A shot has nothing to empty in an empty cup".to_string());
+    }
+    // Syrup takes on the color of whatever it is poured with, so the run
+    // follows the first real color found on either side
+    let mut run_color = b_top.unwrap_or(a_top);
+    // Hidden units stay put even if they turn out to match
     let mut num_a_to_pour = 0;
-    for liq in a.liquids.iter().rev() {
-        if liq == a_top && b.liquids.len() + num_a_to_pour < b.capacity {
+    for (idx, liq) in a.liquids.iter().enumerate().rev() {
+        if liq.matches(run_color)
+            && a.is_revealed(idx)
+            && b.liquids.len() + num_a_to_pour < b.capacity
+        {
+            if *run_color == LiquidColors::Syrup {
+                run_color = liq;
+            }
             num_a_to_pour += 1;
         } else {
             break;
         }
     }
+    // A pour that moves nothing is not a move
+    if num_a_to_pour == 0 {
+        return Err("Nothing can be poured from that cup".to_string());
+    }
     let mut new_a_liquids = a.liquids.clone();
     let mut new_b_liquids = b.liquids.clone();
     for _ in 0..num_a_to_pour {
         let moving_liquid = new_a_liquids.pop();
         new_b_liquids.push(moving_liquid.unwrap());
     }
-    let cup_one = Cup {
+    let mut cup_one = Cup {
         capacity: a.capacity,
         liquids: new_a_liquids,
+        hidden: a.hidden,
+        kind: a.kind.clone(),
     };
+    cup_one.reveal_top();
     let cup_two = Cup {
         capacity: b.capacity,
         liquids: new_b_liquids,
+        hidden: b.hidden,
+        kind: b.kind.clone(),
     };
     Ok((cup_one, cup_two))
 }
@@ -64,10 +234,13 @@ pub fn pour_a_into_b(a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
 //   1. It ignores color difference rules
 //   2. It will not pour all of a color into
 //      a cup, it will only pour one unit
-fn unrestricted_pop_a_into_b(a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
+pub fn unrestricted_pop_a_into_b(a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
     if std::ptr::eq(a, b) {
         return Err("Cannot pour a cup into itself".to_string());
     }
+    if let Some(result) = pour_special(a, b) {
+        return result;
+    }
     if b.capacity <= b.liquids.len() {
         return Err("Destination cup has no space x_x".to_string());
     }
@@ -79,85 +252,53 @@ fn unrestricted_pop_a_into_b(a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
     if let Some(moving_liquid) = new_a.liquids.pop() {
         new_b.liquids.push(moving_liquid);
     }
+    new_a.reveal_top();
     Ok((new_a, new_b))
 }
 
-pub fn scramble_cups(cups: Vec<Cup>) -> Vec<Cup> {
-    println!("Scramble cups' scrambling not implemented, stub running");
-    cups.clone()
-    // let mut cups = vec![
-    //     Cup {
-    //         capacity: 5,
-    //         liquids: vec![
-    //             LiquidColors::Red,
-    //             LiquidColors::Red,
-    //             LiquidColors::Red,
-    //             LiquidColors::Red,
-    //             LiquidColors::Red,
-    //         ],
-    //     },
-    //     Cup {
-    //         capacity: 5,
-    //         liquids: vec![
-    //             LiquidColors::Green,
-    //             LiquidColors::Green,
-    //             LiquidColors::Green,
-    //             LiquidColors::Green,
-    //             LiquidColors::Green,
-    //         ],
-    //     },
-    //     Cup {
-    //         capacity: 5,
-    //         liquids: vec![
-    //             LiquidColors::Blue,
-    //             LiquidColors::Blue,
-    //             LiquidColors::Blue,
-    //             LiquidColors::Blue,
-    //             LiquidColors::Blue,
-    //         ],
-    //     },
-    //     Cup {
-    //         capacity: 5,
-    //         liquids: vec![],
-    //     },
-    //     Cup {
-    //         capacity: 5,
-    //         liquids: vec![],
-    //     },
-    // ];
-    // let iterations = 10000;
-    // for _ in 0..iterations {
-    //     let src_idx = rand::random_range(0..cups.len());
-    //     let dst_idx = rand::random_range(0..cups.len());
-    //     if let Ok((new_src, new_dst)) = unrestricted_pop_a_into_b(&cups[src_idx], &cups[dst_idx]) {
-    //         cups[src_idx] = new_src;
-    //         cups[dst_idx] = new_dst;
-    //     }
-    // }
-    // cups
+// The same cups and seed always scramble into the same board
+pub fn scramble_cups(cups: Vec<Cup>, seed: u64) -> Vec<Cup> {
+    scramble_cups_steps(cups, seed, 10000)
+}
+
+// Fewer steps leave the board closer to solved
+pub fn scramble_cups_steps(cups: Vec<Cup>, seed: u64, steps: usize) -> Vec<Cup> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut cups = cups;
+    for _ in 0..steps {
+        let src_idx = rng.random_range(0..cups.len());
+        let dst_idx = rng.random_range(0..cups.len());
+        if let Ok((new_src, new_dst)) = unrestricted_pop_a_into_b(&cups[src_idx], &cups[dst_idx]) {
+            cups[src_idx] = new_src;
+            cups[dst_idx] = new_dst;
+        }
+    }
+    cups
 }
 
 pub fn are_cups_solved(cups: &[Cup]) -> bool {
     let mut seen_colors: HashSet<LiquidColors> = HashSet::new();
     for c in cups.iter() {
+        // Leftover shots are harmless on their own
+        if c.liquids.iter().all(|l| *l == LiquidColors::Shot) {
+            continue;
+        }
+        // Syrup fits in with any color, so the cup's color is the first
+        // unit that isn't syrup
+        let Some(color) = c.liquids.iter().find(|l| **l != LiquidColors::Syrup) else {
+            continue;
+        };
+
         // First check that the color has not already been seen in another cup
         // if it has not then add it to the set
-        if let Some(l) = c.liquids.last() {
-            if seen_colors.contains(l) {
-                return false;
-            }
-            seen_colors.insert(l.clone());
+        if seen_colors.contains(color) {
+            return false;
         }
+        seen_colors.insert(color.clone());
 
         // Next ensure all colors in the cup are the same
-        let mut last_liquid: Option<LiquidColors> = None;
-        for l in c.liquids.iter() {
-            if let Some(l_liq) = last_liquid {
-                if &l_liq != l {
-                    return false;
-                }
-            }
-            last_liquid = Some(l.clone());
+        if c.liquids.iter().any(|l| !l.matches(color)) {
+            return false;
         }
     }
     true
@@ -172,6 +313,8 @@ mod test_pour_a_into_b {
         let c = Cup {
             capacity: 5,
             liquids: vec![],
+            hidden: 0,
+            kind: CupKind::Normal,
         };
         let r = pour_a_into_b(&c, &c);
         match r {
@@ -185,6 +328,8 @@ mod test_pour_a_into_b {
         let empty_cup = Cup {
             capacity: 5,
             liquids: Vec::new(),
+            hidden: 0,
+            kind: CupKind::Normal,
         };
         let r = pour_a_into_b(&empty_cup.clone(), &empty_cup.clone());
         match r {
@@ -199,10 +344,14 @@ mod test_pour_a_into_b {
             &Cup {
                 capacity: 5,
                 liquids: vec![LiquidColors::Red],
+                hidden: 0,
+                kind: CupKind::Normal,
             },
             &Cup {
                 capacity: 5,
                 liquids: vec![LiquidColors::Green],
+                hidden: 0,
+                kind: CupKind::Normal,
             },
         );
         match r {
@@ -217,10 +366,14 @@ mod test_pour_a_into_b {
             &Cup {
                 capacity: 1,
                 liquids: vec![LiquidColors::Red],
+                hidden: 0,
+                kind: CupKind::Normal,
             },
             &Cup {
                 capacity: 1,
                 liquids: vec![LiquidColors::Green],
+                hidden: 0,
+                kind: CupKind::Normal,
             },
         );
         match r {
@@ -235,10 +388,14 @@ mod test_pour_a_into_b {
             &Cup {
                 capacity: 5,
                 liquids: vec![LiquidColors::Red, LiquidColors::Green, LiquidColors::Blue],
+                hidden: 0,
+                kind: CupKind::Normal,
             },
             &Cup {
                 capacity: 5,
                 liquids: vec![LiquidColors::Green, LiquidColors::Green, LiquidColors::Blue],
+                hidden: 0,
+                kind: CupKind::Normal,
             },
         );
         match r {
@@ -257,4 +414,127 @@ mod test_pour_a_into_b {
             Err(_) => panic!("Expected r to be Ok(..)"),
         }
     }
+
+    #[test]
+    fn hidden_layers_are_revealed() {
+        let r = pour_a_into_b(
+            &Cup {
+                capacity: 4,
+                liquids: vec![LiquidColors::Red, LiquidColors::Green, LiquidColors::Green],
+                hidden: 2,
+                kind: CupKind::Normal,
+            },
+            &Cup {
+                capacity: 4,
+                liquids: vec![],
+                hidden: 0,
+                kind: CupKind::Normal,
+            },
+        );
+        let (c_one, c_two) = r.unwrap();
+        // The hidden green stays behind, but is now on top and revealed
+        assert_eq!(c_one.liquids, vec![LiquidColors::Red, LiquidColors::Green]);
+        assert_eq!(c_one.hidden, 1);
+        assert!(c_one.is_revealed(1));
+        assert_eq!(c_two.liquids, vec![LiquidColors::Green]);
+    }
+
+    #[test]
+    fn special_liquids() {
+        // Syrup joins whichever color it is poured with
+        let (a, b) = pour_a_into_b(
+            &cup(4, vec![LiquidColors::Green, LiquidColors::Red, LiquidColors::Syrup]),
+            &cup(4, vec![LiquidColors::Red]),
+        )
+        .unwrap();
+        assert_eq!(a.liquids, vec![LiquidColors::Green]);
+        assert_eq!(b.liquids.len(), 3);
+        assert!(are_cups_solved(&[a, b]));
+
+        let ice = LiquidColors::Ice(Box::new(LiquidColors::Blue), 2);
+        let mut cups = vec![
+            cup(4, vec![LiquidColors::Blue, ice]),
+            cup(4, vec![LiquidColors::Shot]),
+        ];
+        assert!(pour_a_into_b(&cups[0], &cups[1]).is_err());
+        melt_ice(&mut cups);
+        melt_ice(&mut cups);
+        assert_eq!(cups[0].liquids, vec![LiquidColors::Blue, LiquidColors::Blue]);
+
+        // The shot takes the whole cup with it
+        let (a, b) = pour_a_into_b(&cups[1], &cups[0]).unwrap();
+        assert!(a.liquids.is_empty() && b.liquids.is_empty());
+        // but an empty cup has nothing to take
+        let err = pour_a_into_b(&cups[1], &cup(4, vec![])).unwrap_err();
+        assert_eq!(err, "A shot has nothing to empty in an empty cup");
+    }
+
+    #[test]
+    fn cup_kinds() {
+        let one_way = Cup {
+            kind: CupKind::OneWay,
+            ..cup(4, vec![LiquidColors::Red])
+        };
+        let normal = cup(4, vec![LiquidColors::Red]);
+        assert!(pour_a_into_b(&one_way, &normal).is_err());
+        assert!(pour_a_into_b(&normal, &one_way).is_ok());
+
+        // The lock counts down with every pour on the board
+        let locked = Cup {
+            kind: CupKind::Locked(Lock::Pours(2)),
+            ..cup(4, vec![])
+        };
+        let mut cups = vec![normal.clone(), locked];
+        assert!(pour_a_into_b(&cups[0], &cups[1]).is_err());
+        settle(&mut cups);
+        assert_eq!(cups[1].kind, CupKind::Locked(Lock::Pours(1)));
+        settle(&mut cups);
+        assert!(pour_a_into_b(&cups[0], &cups[1]).is_ok());
+    }
+
+    #[test]
+    fn locks_open_when_a_color_is_sorted() {
+        let red_lock = CupKind::Locked(Lock::Color(LiquidColors::Red));
+        let mut cups = vec![
+            cup(4, vec![LiquidColors::Red, LiquidColors::Red]),
+            cup(4, vec![LiquidColors::Green, LiquidColors::Red]),
+            Cup {
+                kind: red_lock.clone(),
+                ..cup(4, vec![])
+            },
+        ];
+        let err = pour_a_into_b(&cups[1], &cups[2]).unwrap_err();
+        assert_eq!(err, "That cup stays locked until red is sorted");
+        settle(&mut cups);
+        assert_eq!(cups[2].kind, red_lock);
+
+        let (a, b) = pour_a_into_b(&cups[1], &cups[0]).unwrap();
+        cups[1] = a;
+        cups[0] = b;
+        settle(&mut cups);
+        assert_eq!(cups[2].kind, CupKind::Normal);
+    }
+
+    #[test]
+    fn color_locks_wait_for_ice_and_open_for_one_way_cups() {
+        let red_lock = CupKind::Locked(Lock::Color(LiquidColors::Red));
+        let frozen = LiquidColors::Ice(Box::new(LiquidColors::Red), 3);
+        let mut cups = vec![
+            Cup {
+                kind: CupKind::OneWay,
+                ..cup(4, vec![LiquidColors::Red, LiquidColors::Red])
+            },
+            cup(4, vec![frozen]),
+            Cup {
+                kind: red_lock.clone(),
+                ..cup(4, vec![])
+            },
+        ];
+        settle(&mut cups);
+        assert_eq!(cups[2].kind, red_lock);
+
+        cups[1].liquids.clear();
+        settle(&mut cups);
+        assert_eq!(cups[2].kind, CupKind::Normal);
+    }
 }
@@ -0,0 +1,246 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Dates are counted in whole days since 1970-01-01 (UTC)
+pub type Day = i64;
+
+const SHARE_ROW_LENGTH: usize = 10;
+
+pub fn today() -> Day {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO);
+    (since_epoch.as_secs() / 86_400) as Day
+}
+
+// Howard Hinnant's civil_from_days
+pub fn to_ymd(day: Day) -> (i64, u32, u32) {
+    let z = day + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+// Howard Hinnant's days_from_civil
+pub fn from_ymd(y: i64, m: u32, d: u32) -> Day {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = if m > 2 { m - 3 } else { m + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+pub fn format_day(day: Day) -> String {
+    let (y, m, d) = to_ymd(day);
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+pub fn parse_day(s: &str) -> Option<Day> {
+    let mut parts = s.split('-');
+    let y = parts.next()?.parse().ok()?;
+    let m = parts.next()?.parse().ok()?;
+    let d = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return None;
+    }
+    Some(from_ymd(y, m, d))
+}
+
+// Reads as the date, e.g. 20261019, so it doubles as a board number
+pub fn daily_seed(day: Day) -> u64 {
+    let (y, m, d) = to_ymd(day);
+    (y as u64) * 10_000 + (m as u64) * 100 + d as u64
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DailyRecord {
+    pub day: Day,
+    pub moves: usize,
+    pub par: Option<usize>,
+    pub time_taken: Duration,
+}
+
+impl DailyRecord {
+    pub fn to_line(&self) -> String {
+        let par = match self.par {
+            Some(par) => par.to_string(),
+            None => "-".to_string(),
+        };
+        format!(
+            "daily date={} moves={} par={} secs={}",
+            format_day(self.day),
+            self.moves,
+            par,
+            self.time_taken.as_secs()
+        )
+    }
+
+    pub fn from_line(line: &str) -> Option<DailyRecord> {
+        let mut words = line.split_whitespace();
+        if words.next()? != "daily" {
+            return None;
+        }
+        let mut day = None;
+        let mut moves = None;
+        let mut par = None;
+        let mut secs = None;
+        for word in words {
+            let (key, value) = word.split_once('=')?;
+            match key {
+                "date" => day = parse_day(value),
+                "moves" => moves = value.parse().ok(),
+                "par" => par = Some(value.parse().ok()),
+                "secs" => secs = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(DailyRecord {
+            day: day?,
+            moves: moves?,
+            par: par?,
+            time_taken: Duration::from_secs(secs?),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DailyStats {
+    pub completed_today: bool,
+    // Days in a row with a finished daily, still counted if
+    // today's has not been played yet
+    pub streak: usize,
+    pub best_streak: usize,
+}
+
+impl DailyStats {
+    pub fn from_records(records: &[DailyRecord], today: Day) -> DailyStats {
+        let mut days: Vec<Day> = records.iter().map(|r| r.day).collect();
+        days.sort();
+        days.dedup();
+
+        let mut stats = DailyStats::default();
+        let mut run = 0;
+        let mut last_day = None;
+        for day in days.iter() {
+            run = match last_day {
+                Some(last) if last + 1 == *day => run + 1,
+                _ => 1,
+            };
+            stats.best_streak = stats.best_streak.max(run);
+            last_day = Some(*day);
+        }
+        stats.completed_today = last_day == Some(today);
+        if last_day.is_some_and(|last| last >= today - 1) {
+            stats.streak = run;
+        }
+        stats
+    }
+}
+
+// A spoiler free summary to paste into chat. One square per pour,
+// green while under par, yellow for up to half again over par
+// and red after that.
+pub fn share_text(
+    day: Day,
+    moves: usize,
+    par: Option<usize>,
+    hints_used: usize,
+    stars: Option<usize>,
+) -> String {
+    let mut lines = vec![format!("terminal barista ☕️ Daily Blend {}", format_day(day))];
+    let par_text = match par {
+        Some(par) => format!(" (par {})", par),
+        None => "".to_string(),
+    };
+    let stars_text = match stars {
+        Some(stars) => "⭐".repeat(stars) + " ",
+        None => "".to_string(),
+    };
+    let hints_text = match hints_used {
+        0 => "".to_string(),
+        n => format!(" {}", "💡".repeat(n)),
+    };
+    lines.push(format!("{}{} pours{}{}", stars_text, moves, par_text, hints_text));
+
+    let squares: Vec<&str> = (0..moves)
+        .map(|i| match par {
+            Some(par) if i < par => "🟩",
+            Some(par) if i < par + par / 2 => "🟨",
+            Some(_) => "🟥",
+            None => "🟦",
+        })
+        .collect();
+    for row in squares.chunks(SHARE_ROW_LENGTH) {
+        lines.push(row.concat());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test_daily {
+    use super::*;
+
+    #[test]
+    fn dates_round_trip() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(parse_day("2026-10-19"), Some(20_745));
+        assert_eq!(format_day(20_745), "2026-10-19");
+        assert_eq!(format_day(parse_day("2024-02-29").unwrap()), "2024-02-29");
+        assert_eq!(parse_day("2024-13-01"), None);
+        assert_eq!(daily_seed(20_745), 20_261_019);
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let r = DailyRecord {
+            day: 20_745,
+            moves: 31,
+            par: Some(28),
+            time_taken: Duration::from_secs(95),
+        };
+        assert_eq!(DailyRecord::from_line(&r.to_line()), Some(r));
+    }
+
+    #[test]
+    fn streaks() {
+        let record = |day| DailyRecord {
+            day,
+            moves: 1,
+            par: None,
+            time_taken: Duration::ZERO,
+        };
+        let records = vec![record(10), record(11), record(12), record(20), record(21)];
+        let stats = DailyStats::from_records(&records, 22);
+        assert_eq!(stats.streak, 2);
+        assert_eq!(stats.best_streak, 3);
+        assert!(!stats.completed_today);
+
+        let stats = DailyStats::from_records(&records, 21);
+        assert!(stats.completed_today);
+
+        let stats = DailyStats::from_records(&records, 23);
+        assert_eq!(stats.streak, 0);
+    }
+
+    #[test]
+    fn share_grid() {
+        let text = share_text(20_745, 12, Some(8), 1, Some(2));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "terminal barista ☕️ Daily Blend 2026-10-19",
+                "⭐⭐ 12 pours (par 8) 💡",
+                "🟩🟩🟩🟩🟩🟩🟩🟩🟨🟨",
+                "🟨🟨",
+            ]
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+use crate::cup::Cup;
+use crate::liquids::LiquidColors;
+use crate::pour_rule::PourRule;
+use crate::solver::{explore, solve};
+use crate::win_condition::WinCondition;
+
+// Boards explored when looking for dead ends, enough to see the whole
+// of a small board and the start of a big one
+const EXPLORE_LIMIT: usize = 20_000;
+
+// What makes a board hard, worked out from the board itself rather
+// than how it was generated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rating {
+    // Length of the optimal solution, None if the solver found none
+    pub par: Option<usize>,
+    // Average number of legal pours from the boards explored
+    pub branching: f64,
+    // Share of the explored boards the goal can't be reached from
+    pub dead_ends: f64,
+    // Share of the total capacity left empty
+    pub spare: f64,
+    // Extra runs of color beyond one per color, per unit of liquid
+    pub fragmentation: f64,
+    // 0 for a sorted board up to 10, unsolvable boards get 10
+    pub score: f64,
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let par = match self.par {
+            Some(par) => format!("par {}", par),
+            None => "no solution found".to_string(),
+        };
+        write!(
+            f,
+            "{:.1}/10 ({}, {:.1} pours per board, {:.0}% dead ends)",
+            self.score,
+            par,
+            self.branching,
+            self.dead_ends * 100.0
+        )
+    }
+}
+
+fn fragmentation(cups: &[Cup]) -> f64 {
+    let units: Vec<&LiquidColors> = cups.iter().flat_map(|c| c.liquids.iter()).collect();
+    if units.is_empty() {
+        return 0.0;
+    }
+    let mut colors = units.clone();
+    colors.sort();
+    colors.dedup();
+    let runs: usize = cups
+        .iter()
+        .map(|c| {
+            (0..c.liquids.len())
+                .filter(|i| *i == 0 || c.liquids[i - 1] != c.liquids[*i])
+                .count()
+        })
+        .sum();
+    (runs - colors.len()) as f64 / units.len() as f64
+}
+
+pub fn rate(cups: &[Cup], goal: &dyn WinCondition, rule: &dyn PourRule) -> Rating {
+    let par = solve(cups, goal, rule).map(|pours| pours.len());
+    let space = explore(cups, goal, rule, EXPLORE_LIMIT);
+    let pours: usize = space.pours.iter().map(|p| p.len()).sum();
+    let branching = pours as f64 / space.expanded.max(1) as f64;
+    let dead_ends = space.dead_ends(goal).len() as f64 / space.expanded.max(1) as f64;
+    let capacity: usize = cups.iter().map(|c| c.capacity).sum();
+    let filled: usize = cups.iter().map(|c| c.liquids.len()).sum();
+    let spare = 1.0 - filled as f64 / capacity.max(1) as f64;
+    let fragmentation = fragmentation(cups);
+
+    // Longer solutions count the most, though each extra pour adds a
+    // little less. Many choices, traps, little room to work with and
+    // scattered colors each make it harder to find the way through.
+    let score = match par {
+        Some(0) => 0.0,
+        Some(par) => {
+            let length = 7.0 * (1.0 - (-(par as f64) / 20.0).exp());
+            let choices = (branching / 8.0).min(1.0);
+            let traps = dead_ends.sqrt() * 2.0;
+            let cramped = (0.4 - spare).max(0.0) * 5.0;
+            (length + choices + traps + cramped + fragmentation * 1.5).min(10.0)
+        }
+        None => 10.0,
+    };
+    Rating {
+        par,
+        branching,
+        dead_ends,
+        spare,
+        fragmentation,
+        score,
+    }
+}
+
+#[cfg(test)]
+mod test_difficulty {
+    use super::*;
+    use crate::cup::{cup, scramble_cups};
+    use crate::level_creator::{generate_board, generate_cups, BoardParams, SimpleDifficulties};
+    use crate::pour_rule::Standard;
+    use crate::win_condition::UniqueMonochrome;
+
+    #[test]
+    fn sorted_and_stuck_boards() {
+        let sorted = generate_cups(SimpleDifficulties::Easy);
+        assert_eq!(rate(&sorted, &UniqueMonochrome, &Standard).score, 0.0);
+
+        let stuck = vec![
+            cup(2, vec![LiquidColors::Red, LiquidColors::Green]),
+            cup(2, vec![LiquidColors::Green, LiquidColors::Red]),
+        ];
+        let rating = rate(&stuck, &UniqueMonochrome, &Standard);
+        assert_eq!(rating.par, None);
+        assert_eq!(rating.dead_ends, 1.0);
+        assert_eq!(rating.score, 10.0);
+    }
+
+    #[test]
+    fn bigger_boards_rate_higher() {
+        let easy = scramble_cups(generate_cups(SimpleDifficulties::Easy), 3);
+        let bigger = generate_board(BoardParams::endless(4), 3);
+        let easy = rate(&easy, &UniqueMonochrome, &Standard);
+        let bigger = rate(&bigger, &UniqueMonochrome, &Standard);
+        assert!(easy.score < bigger.score, "{} vs {}", easy, bigger);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::cup::{Cup, CupKind, Lock};
+use crate::liquids::LiquidColors;
+
+// Liquids the editor paints with, ↑ and ↓ step through them
+pub const PAINTS: [LiquidColors; 10] = [
+    LiquidColors::Red,
+    LiquidColors::Green,
+    LiquidColors::Blue,
+    LiquidColors::BabyBlue,
+    LiquidColors::Pink,
+    LiquidColors::Yellow,
+    LiquidColors::Brown,
+    LiquidColors::White,
+    LiquidColors::Syrup,
+    LiquidColors::Shot,
+];
+// Cups are picked with the keys 1-9 and 0 while playing
+const MAX_CUPS: usize = 10;
+const MAX_CAPACITY: usize = 8;
+// Ice and locks are painted with a fixed count, the level file can
+// be edited by hand for anything else
+const ICE_MOVES: usize = 5;
+const LOCK_POURS: usize = 5;
+
+// Where the player is on a board they are building. The cups themselves
+// live on the app so the board is drawn just like one being played.
+#[derive(Debug, Default, Clone)]
+pub struct Editor {
+    // The cup being edited
+    pub cursor: usize,
+    // Index into PAINTS
+    pub paint: usize,
+    // The board as it was last saved, leaving with other changes asks
+    // first
+    pub saved: Vec<Cup>,
+    // Set once Esc has been pressed over unsaved changes
+    pub leaving: bool,
+}
+
+impl Editor {
+    pub fn paint_color(&self) -> &LiquidColors {
+        &PAINTS[self.paint]
+    }
+
+    // Leaving a board with unsaved changes takes a second Esc
+    pub fn can_leave(&mut self, cups: &[Cup]) -> bool {
+        if self.leaving || self.saved == cups {
+            return true;
+        }
+        self.leaving = true;
+        false
+    }
+
+    pub fn move_cursor(&mut self, cups: &[Cup], right: bool) {
+        self.cursor = match right {
+            true => (self.cursor + 1).min(cups.len().saturating_sub(1)),
+            false => self.cursor.saturating_sub(1),
+        };
+    }
+
+    pub fn select(&mut self, cups: &[Cup], idx: usize) -> Result<(), String> {
+        if idx >= cups.len() {
+            return Err("There is no cup there".to_string());
+        }
+        self.cursor = idx;
+        Ok(())
+    }
+
+    pub fn cycle_paint(&mut self, forward: bool) {
+        self.paint = match forward {
+            true => (self.paint + 1) % PAINTS.len(),
+            false => (self.paint + PAINTS.len() - 1) % PAINTS.len(),
+        };
+    }
+
+    // A new empty cup the same size as the one under the cursor goes
+    // to its right
+    pub fn add_cup(&mut self, cups: &mut Vec<Cup>) -> Result<(), String> {
+        if cups.len() >= MAX_CUPS {
+            return Err(format!("A board can have at most {} cups", MAX_CUPS));
+        }
+        let capacity = cups.get(self.cursor).map_or(4, |c| c.capacity);
+        let idx = (self.cursor + 1).min(cups.len());
+        cups.insert(
+            idx,
+            Cup {
+                capacity,
+                liquids: vec![],
+                hidden: 0,
+                kind: CupKind::Normal,
+            },
+        );
+        self.cursor = idx;
+        Ok(())
+    }
+
+    pub fn remove_cup(&mut self, cups: &mut Vec<Cup>) -> Result<(), String> {
+        if cups.len() <= 1 {
+            return Err("A board needs at least one cup".to_string());
+        }
+        cups.remove(self.cursor);
+        self.cursor = self.cursor.min(cups.len() - 1);
+        Ok(())
+    }
+
+    pub fn resize(&self, cups: &mut [Cup], bigger: bool) -> Result<(), String> {
+        let cup = &mut cups[self.cursor];
+        match bigger {
+            true if cup.capacity >= MAX_CAPACITY => {
+                Err(format!("Cups hold at most {} units", MAX_CAPACITY))
+            }
+            true => {
+                cup.capacity += 1;
+                Ok(())
+            }
+            false if cup.capacity <= 1 || cup.capacity <= cup.liquids.len() => {
+                Err("Take some liquid out before making the cup smaller".to_string())
+            }
+            false => {
+                cup.capacity -= 1;
+                Ok(())
+            }
+        }
+    }
+
+    // Pours are built up bottom first, so painting adds on top
+    pub fn paint(&self, cups: &mut [Cup]) -> Result<(), String> {
+        let cup = &mut cups[self.cursor];
+        if cup.liquids.len() >= cup.capacity {
+            return Err("That cup is full".to_string());
+        }
+        cup.liquids.push(self.paint_color().clone());
+        Ok(())
+    }
+
+    pub fn erase(&self, cups: &mut [Cup]) -> Result<(), String> {
+        cups[self.cursor]
+            .liquids
+            .pop()
+            .map(|_| ())
+            .ok_or("That cup is already empty".to_string())
+    }
+
+    // Freezes the top unit, or thaws it if it is already ice
+    pub fn toggle_ice(&self, cups: &mut [Cup]) -> Result<(), String> {
+        let top = cups[self.cursor]
+            .liquids
+            .last_mut()
+            .ok_or("Paint something to freeze first")?;
+        *top = match &*top {
+            LiquidColors::Ice(color, _) => (**color).clone(),
+            LiquidColors::Syrup | LiquidColors::Shot => {
+                return Err("Only plain colors can be frozen".to_string());
+            }
+            color => LiquidColors::Ice(Box::new(color.clone()), ICE_MOVES),
+        };
+        Ok(())
+    }
+
+    // Normal, one-way, locked for a few pours, then locked until the
+    // paint color is sorted
+    pub fn cycle_kind(&self, cups: &mut [Cup]) {
+        let cup = &mut cups[self.cursor];
+        let color = match self.paint_color() {
+            color if color.is_special() => LiquidColors::Red,
+            color => color.clone(),
+        };
+        cup.kind = match cup.kind {
+            CupKind::Normal => CupKind::OneWay,
+            CupKind::OneWay => CupKind::Locked(Lock::Pours(LOCK_POURS)),
+            CupKind::Locked(Lock::Pours(_)) => CupKind::Locked(Lock::Color(color)),
+            CupKind::Locked(Lock::Color(_)) => CupKind::Normal,
+        };
+    }
+}
+
+#[cfg(test)]
+mod test_editor {
+    use super::*;
+    use crate::cup::cup;
+
+    #[test]
+    fn builds_a_board() {
+        let mut editor = Editor::default();
+        let mut cups = vec![cup(2, vec![])];
+        editor.paint(&mut cups).unwrap();
+        editor.cycle_paint(true);
+        editor.paint(&mut cups).unwrap();
+        assert!(editor.paint(&mut cups).is_err());
+        assert_eq!(cups[0].liquids, vec![LiquidColors::Red, LiquidColors::Green]);
+        assert!(editor.resize(&mut cups, false).is_err());
+
+        editor.add_cup(&mut cups).unwrap();
+        assert_eq!(editor.cursor, 1);
+        assert_eq!(cups[1].capacity, 2);
+        editor.remove_cup(&mut cups).unwrap();
+        assert!(editor.remove_cup(&mut cups).is_err());
+        assert_eq!(editor.cursor, 0);
+    }
+
+    #[test]
+    fn asks_before_leaving_unsaved_changes() {
+        let mut cups = vec![cup(2, vec![])];
+        let mut editor = Editor {
+            saved: cups.clone(),
+            ..Default::default()
+        };
+        assert!(editor.can_leave(&cups));
+        editor.paint(&mut cups).unwrap();
+        assert!(!editor.can_leave(&cups));
+        assert!(editor.can_leave(&cups));
+    }
+
+    #[test]
+    fn freezes_and_locks() {
+        let editor = Editor::default();
+        let mut cups = vec![cup(4, vec![])];
+        assert!(editor.toggle_ice(&mut cups).is_err());
+        editor.paint(&mut cups).unwrap();
+        editor.toggle_ice(&mut cups).unwrap();
+        assert_eq!(
+            cups[0].liquids,
+            vec![LiquidColors::Ice(Box::new(LiquidColors::Red), ICE_MOVES)]
+        );
+        editor.toggle_ice(&mut cups).unwrap();
+        assert_eq!(cups[0].liquids, vec![LiquidColors::Red]);
+
+        editor.cycle_kind(&mut cups);
+        editor.cycle_kind(&mut cups);
+        editor.cycle_kind(&mut cups);
+        assert_eq!(cups[0].kind, CupKind::Locked(Lock::Color(LiquidColors::Red)));
+    }
+}
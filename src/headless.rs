@@ -0,0 +1,300 @@
+use std::io::{self, BufRead, Write};
+
+use crate::app::{App, CurrentScreen, GameMode};
+use crate::cup::Cup;
+use crate::level::{kind_to_token, liquid_to_token};
+use crate::solver::Pour;
+
+// Plays the game over stdin and stdout instead of a terminal, for bots
+// and scripted tests. Each line read is one command:
+//
+//   state             the board as it is
+//   pour <from> <to>  pours between cups numbered from 1, like the keys
+//   undo              takes back the last pour
+//   restart           back to the starting board
+//   hint              the next pour towards the goal
+//   next              a new board once this one is finished
+//   quit              stops reading commands
+//
+// Every command is answered with the board, a hint or an error. Text
+// replies end with a blank line, JSON replies are one object per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+enum Reply {
+    Board,
+    Hint(Option<Pour>),
+    Error(String),
+}
+
+fn cup_number(word: &str) -> Option<usize> {
+    word.parse::<usize>().ok()?.checked_sub(1)
+}
+
+// The same moves the keys allow on each screen
+fn run_command(app: &mut App, line: &str) -> Reply {
+    let playing = app.current_screen == CurrentScreen::Main;
+    // A limited board that ran out of pours can still be taken back,
+    // and so can a board that got stuck
+    let retry = (app.mode == GameMode::Limited && app.current_screen == CurrentScreen::GameOver)
+        || app.current_screen == CurrentScreen::Stuck;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let result = match words[..] {
+        ["state"] => Ok(()),
+        ["pour", src, dst] if playing => match (cup_number(src), cup_number(dst)) {
+            (Some(src), Some(dst)) => app.pour(src, dst),
+            _ => Err("Cups are numbered from 1".to_string()),
+        },
+        ["undo"] if playing || retry => app.undo(),
+        ["restart"] if playing || retry => {
+            app.restart();
+            Ok(())
+        }
+        ["hint"] if playing => return Reply::Hint(app.hint()),
+        // Levels, share codes and the Daily Blend go back to the menu or
+        // the editor, which can't be played from here
+        ["next"] if app.current_screen == CurrentScreen::Finished => {
+            let next = app.next_game();
+            match next.current_screen {
+                CurrentScreen::Main => {
+                    *app = next;
+                    Ok(())
+                }
+                _ => Err("There is no next board after this one".to_string()),
+            }
+        }
+        ["pour", _, _] | ["undo"] | ["restart"] | ["hint"] | ["next"] => Err(format!(
+            "Can't {} now, the board is {}",
+            words[0],
+            status(app)
+        )),
+        _ => Err(
+            "Unknown command, try state, pour <from> <to>, undo, restart, hint, next or quit"
+                .to_string(),
+        ),
+    };
+    match result {
+        Ok(()) => Reply::Board,
+        Err(e) => Reply::Error(e),
+    }
+}
+
+fn status(app: &App) -> &'static str {
+    match app.current_screen {
+        CurrentScreen::Finished => "won",
+        CurrentScreen::GameOver => "lost",
+        CurrentScreen::Stuck => "stuck",
+        CurrentScreen::Main => "playing",
+        CurrentScreen::Welcome => "on the menu",
+        CurrentScreen::Editor => "being edited",
+        CurrentScreen::Statistics | CurrentScreen::HighScores => "paused",
+    }
+}
+
+// Hidden units stay hidden, a bot sees no more than a player would
+fn liquid_tokens(cup: &Cup) -> Vec<String> {
+    cup.liquids
+        .iter()
+        .enumerate()
+        .map(|(i, liquid)| match cup.is_revealed(i) {
+            true => liquid_to_token(liquid),
+            false => "?".to_string(),
+        })
+        .collect()
+}
+
+pub fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn json_number(n: Option<usize>) -> String {
+    n.map_or("null".to_string(), |n| n.to_string())
+}
+
+fn board_text(app: &App) -> String {
+    let mut header = vec![format!("moves {}", app.moves)];
+    header.push(format!(
+        "par {}",
+        app.par.map_or("-".to_string(), |p| p.to_string())
+    ));
+    if let Some(left) = app.moves_left() {
+        header.push(format!("left {}", left));
+    }
+    header.push(status(app).to_string());
+    let mut lines = vec![header.join(" ")];
+    for (i, cup) in app.cups.iter().enumerate() {
+        let mut words: Vec<String> = kind_to_token(&cup.kind).into_iter().collect();
+        words.push(cup.capacity.to_string());
+        words.extend(liquid_tokens(cup));
+        lines.push(format!("{}: {}", i + 1, words.join(" ")));
+    }
+    lines.join("\n")
+}
+
+fn board_json(app: &App) -> String {
+    let cups: Vec<String> = app
+        .cups
+        .iter()
+        .map(|cup| {
+            let kind = kind_to_token(&cup.kind).unwrap_or("normal".to_string());
+            let liquids: Vec<String> = liquid_tokens(cup).iter().map(|t| json_string(t)).collect();
+            format!(
+                "{{\"capacity\":{},\"kind\":{},\"liquids\":[{}]}}",
+                cup.capacity,
+                json_string(&kind),
+                liquids.join(",")
+            )
+        })
+        .collect();
+    format!(
+        "{{\"moves\":{},\"par\":{},\"moves_left\":{},\"status\":{},\"cups\":[{}]}}",
+        app.moves,
+        json_number(app.par),
+        json_number(app.moves_left()),
+        json_string(status(app)),
+        cups.join(",")
+    )
+}
+
+fn reply_text(app: &App, format: Format, reply: &Reply) -> String {
+    match (format, reply) {
+        (Format::Text, Reply::Board) => board_text(app) + "\n",
+        (Format::Text, Reply::Hint(Some((src, dst)))) => format!("hint {} {}\n", src + 1, dst + 1),
+        (Format::Text, Reply::Hint(None)) => "hint none\n".to_string(),
+        (Format::Text, Reply::Error(e)) => format!("error {}\n", e),
+        (Format::Json, Reply::Board) => board_json(app),
+        (Format::Json, Reply::Hint(Some((src, dst)))) => {
+            format!("{{\"hint\":[{},{}]}}", src + 1, dst + 1)
+        }
+        (Format::Json, Reply::Hint(None)) => "{\"hint\":null}".to_string(),
+        (Format::Json, Reply::Error(e)) => format!("{{\"error\":{}}}", json_string(e)),
+    }
+}
+
+// Starts with the board so a bot knows what it is playing. Nothing
+// played here goes into the player's stats or replays.
+pub fn run_headless(
+    app: &mut App,
+    format: Format,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    app.scripted = true;
+    writeln!(output, "{}", reply_text(app, format, &Reply::Board))?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        match line.trim() {
+            "" => continue,
+            "quit" => break,
+            command => {
+                // Keeps a timed board's clock running between commands
+                app.tick();
+                let reply = run_command(app, command);
+                writeln!(output, "{}", reply_text(app, format, &reply))?;
+                output.flush()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_headless {
+    use super::*;
+    use crate::cup::cup;
+    use crate::liquids::LiquidColors;
+
+    fn app() -> App {
+        App {
+            current_screen: CurrentScreen::Main,
+            cups: vec![
+                Cup {
+                    hidden: 1,
+                    ..cup(3, vec![LiquidColors::Red, LiquidColors::Green, LiquidColors::Red])
+                },
+                cup(3, vec![LiquidColors::Green, LiquidColors::Red, LiquidColors::Green]),
+                cup(3, vec![]),
+            ],
+            par: Some(4),
+            ..Default::default()
+        }
+    }
+
+    fn play(format: Format, commands: &str) -> String {
+        let mut output = vec![];
+        run_headless(&mut app(), format, commands.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn text_replies() {
+        let output = play(
+            Format::Text,
+            "pour 1 3\npour 1 2\nundo\nhint\nfly\nquit\nstate\n",
+        );
+        let replies: Vec<&str> = output.split("\n\n").collect();
+        assert_eq!(
+            replies[0],
+            "moves 0 par 4 playing\n1: 3 ? green red\n2: 3 green red green\n3: 3"
+        );
+        assert_eq!(
+            replies[1],
+            "moves 1 par 4 playing\n1: 3 ? green\n2: 3 green red green\n3: 3 red"
+        );
+        assert_eq!(replies[2], "error Destination cup has no space x_x");
+        assert!(replies[3].starts_with("moves 0 "));
+        assert!(replies[4].starts_with("hint "));
+        assert!(replies[5].starts_with("error Unknown command"));
+        // Nothing is read after quit
+        assert_eq!(replies.len(), 7);
+
+        let mut app = app();
+        run_headless(&mut app, Format::Text, "".as_bytes(), vec![]).unwrap();
+        assert!(app.scripted);
+    }
+
+    #[test]
+    fn custom_levels_have_no_next_board() {
+        let mut app = App {
+            current_screen: CurrentScreen::Finished,
+            mode: GameMode::Custom,
+            ..app()
+        };
+        let mut output = vec![];
+        run_headless(&mut app, Format::Text, "next\npour 1 3\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let replies: Vec<&str> = output.split("\n\n").collect();
+        assert_eq!(replies[1], "error There is no next board after this one");
+        assert_eq!(replies[2], "error Can't pour now, the board is won");
+        assert_eq!(app.current_screen, CurrentScreen::Finished);
+        assert_eq!(status(&App::new()), "on the menu");
+    }
+
+    #[test]
+    fn json_replies() {
+        let output = play(Format::Json, "pour 0 1\nstate\n");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1], "{\"error\":\"Cups are numbered from 1\"}");
+        assert_eq!(
+            lines[2],
+            "{\"moves\":0,\"par\":4,\"moves_left\":null,\"status\":\"playing\",\"cups\":[\
+             {\"capacity\":3,\"kind\":\"normal\",\"liquids\":[\"?\",\"green\",\"red\"]},\
+             {\"capacity\":3,\"kind\":\"normal\",\"liquids\":[\"green\",\"red\",\"green\"]},\
+             {\"capacity\":3,\"kind\":\"normal\",\"liquids\":[]}]}"
+        );
+    }
+}
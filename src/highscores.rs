@@ -0,0 +1,161 @@
+use std::{io, time::Duration};
+
+use crate::level_creator::SimpleDifficulties;
+use crate::storage::{append_line, read_lines};
+
+const HIGH_SCORES_FILE: &str = "highscores.log";
+pub const LEADERBOARD_SIZE: usize = 10;
+pub const MAX_NAME_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighScore {
+    pub difficulty: SimpleDifficulties,
+    // Together with the difficulty this identifies the board
+    pub seed: u64,
+    pub moves: usize,
+    pub time_taken: Duration,
+    pub name: String,
+}
+
+impl HighScore {
+    // The name goes last so it can hold spaces
+    pub fn to_line(&self) -> String {
+        format!(
+            "score difficulty={} seed={} moves={} secs={} name={}",
+            self.difficulty,
+            self.seed,
+            self.moves,
+            self.time_taken.as_secs(),
+            self.name
+        )
+    }
+
+    pub fn from_line(line: &str) -> Option<HighScore> {
+        let rest = line.strip_prefix("score ")?;
+        let (fields, name) = rest.split_once(" name=")?;
+        let mut difficulty = None;
+        let mut seed = None;
+        let mut moves = None;
+        let mut secs = None;
+        for word in fields.split_whitespace() {
+            let (key, value) = word.split_once('=')?;
+            match key {
+                "difficulty" => difficulty = SimpleDifficulties::from_name(value),
+                "seed" => seed = value.parse().ok(),
+                "moves" => moves = value.parse().ok(),
+                "secs" => secs = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(HighScore {
+            difficulty: difficulty?,
+            seed: seed?,
+            moves: moves?,
+            time_taken: Duration::from_secs(secs?),
+            name: name.to_string(),
+        })
+    }
+
+    fn beats(&self, other: &HighScore) -> bool {
+        (self.moves, self.time_taken) < (other.moves, other.time_taken)
+    }
+}
+
+// Fewest moves first, then fastest. Ties keep the earlier score on top.
+fn ranked<'a>(scores: impl Iterator<Item = &'a HighScore>) -> Vec<&'a HighScore> {
+    let mut scores: Vec<&HighScore> = scores.collect();
+    scores.sort_by_key(|s| (s.moves, s.time_taken));
+    scores.truncate(LEADERBOARD_SIZE);
+    scores
+}
+
+pub fn level_leaderboard(
+    scores: &[HighScore],
+    difficulty: SimpleDifficulties,
+    seed: u64,
+) -> Vec<&HighScore> {
+    ranked(
+        scores
+            .iter()
+            .filter(|s| s.difficulty == difficulty && s.seed == seed),
+    )
+}
+
+pub fn difficulty_leaderboard(
+    scores: &[HighScore],
+    difficulty: SimpleDifficulties,
+) -> Vec<&HighScore> {
+    ranked(scores.iter().filter(|s| s.difficulty == difficulty))
+}
+
+// A score is worth saving if it would show up on either leaderboard
+pub fn makes_leaderboard(scores: &[HighScore], candidate: &HighScore) -> bool {
+    let qualifies = |board: Vec<&HighScore>| {
+        board.len() < LEADERBOARD_SIZE || board.iter().any(|s| candidate.beats(s))
+    };
+    qualifies(level_leaderboard(scores, candidate.difficulty, candidate.seed))
+        || qualifies(difficulty_leaderboard(scores, candidate.difficulty))
+}
+
+pub fn save_score(score: &HighScore) -> io::Result<()> {
+    append_line(HIGH_SCORES_FILE, &score.to_line())
+}
+
+pub fn load_scores() -> io::Result<Vec<HighScore>> {
+    Ok(read_lines(HIGH_SCORES_FILE)?
+        .iter()
+        .filter_map(|l| HighScore::from_line(l))
+        .collect())
+}
+
+#[cfg(test)]
+mod test_highscores {
+    use super::*;
+
+    fn score(seed: u64, moves: usize, secs: u64, name: &str) -> HighScore {
+        HighScore {
+            difficulty: SimpleDifficulties::Hard,
+            seed,
+            moves,
+            time_taken: Duration::from_secs(secs),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn line_round_trip() {
+        let s = score(48213, 31, 95, "Arman D");
+        assert_eq!(HighScore::from_line(&s.to_line()), Some(s));
+    }
+
+    #[test]
+    fn ranks_by_moves_then_time() {
+        let scores = vec![
+            score(1, 30, 100, "slow"),
+            score(1, 30, 60, "fast"),
+            score(1, 25, 200, "few moves"),
+            score(2, 10, 10, "other board"),
+        ];
+        let names: Vec<&str> = level_leaderboard(&scores, SimpleDifficulties::Hard, 1)
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["few moves", "fast", "slow"]);
+        assert_eq!(
+            difficulty_leaderboard(&scores, SimpleDifficulties::Hard)[0].name,
+            "other board"
+        );
+    }
+
+    #[test]
+    fn full_leaderboard_needs_a_better_score() {
+        let mut scores: Vec<HighScore> = (0..LEADERBOARD_SIZE)
+            .map(|i| score(1, 20 + i, 60, "player"))
+            .collect();
+        // Push the difficulty board full with better scores from other boards
+        scores.extend((0..LEADERBOARD_SIZE).map(|_| score(2, 5, 60, "player")));
+        assert!(!makes_leaderboard(&scores, &score(1, 40, 60, "me")));
+        assert!(makes_leaderboard(&scores, &score(1, 25, 10, "me")));
+        assert!(makes_leaderboard(&scores, &score(3, 40, 60, "me")));
+    }
+}
@@ -0,0 +1,248 @@
+use std::{fs, path::PathBuf};
+
+use crate::cup::{Cup, CupKind, Lock};
+use crate::liquids::LiquidColors;
+use crate::storage::save_to_data_dir;
+
+// A level file lists one cup per line as its capacity followed by its
+// liquids, bottom first:
+//
+//   # two colors, a pint glass and a spare that opens after 3 pours
+//   4 red green syrup red
+//   6 green ice:red:3 green shot
+//   locked:3 4
+//
+// A cup line can start with `oneway` to make it a one-way cup, or with
+// `locked:<pours>` or `locked:<color>` for a cup that opens after that
+// many pours or once that color is sorted. `hidden:<n>` before the
+// capacity hides the bottom n units, like on a mystery board, and the
+// top unit always shows. Lines starting with # are
+// comments. Optional `goal <name>` and `rules <name>` lines take the
+// same names as --goal and --rules.
+#[derive(Debug, Clone, Default)]
+pub struct Level {
+    pub cups: Vec<Cup>,
+    pub goal: Option<String>,
+    pub rules: Option<String>,
+}
+
+fn color_from_name(name: &str) -> Option<LiquidColors> {
+    match name {
+        "red" => Some(LiquidColors::Red),
+        "green" => Some(LiquidColors::Green),
+        "blue" => Some(LiquidColors::Blue),
+        "pink" => Some(LiquidColors::Pink),
+        "babyblue" => Some(LiquidColors::BabyBlue),
+        "yellow" => Some(LiquidColors::Yellow),
+        "brown" => Some(LiquidColors::Brown),
+        "white" => Some(LiquidColors::White),
+        "syrup" => Some(LiquidColors::Syrup),
+        "shot" => Some(LiquidColors::Shot),
+        _ => None,
+    }
+}
+
+fn kind_from_token(token: &str) -> Option<CupKind> {
+    match token.split(':').collect::<Vec<&str>>()[..] {
+        ["oneway"] => Some(CupKind::OneWay),
+        ["locked", trigger] => {
+            let lock = match trigger.parse::<usize>() {
+                Ok(pours) if pours > 0 => Lock::Pours(pours),
+                Ok(_) => return None,
+                Err(_) => Lock::Color(color_from_name(trigger).filter(|c| !c.is_special())?),
+            };
+            Some(CupKind::Locked(lock))
+        }
+        _ => None,
+    }
+}
+
+// Ice is written as ice:<color>:<moves until it melts>
+pub fn liquid_from_token(token: &str) -> Option<LiquidColors> {
+    match token.split(':').collect::<Vec<&str>>()[..] {
+        ["ice", color, moves] => Some(LiquidColors::Ice(
+            Box::new(color_from_name(color).filter(|c| !c.is_special())?),
+            moves.parse().ok().filter(|m| *m > 0)?,
+        )),
+        [name] => color_from_name(name),
+        _ => None,
+    }
+}
+
+pub fn liquid_to_token(liquid: &LiquidColors) -> String {
+    match liquid {
+        LiquidColors::Ice(color, moves) => format!("ice:{}:{}", liquid_to_token(color), moves),
+        _ => liquid.to_string().to_lowercase(),
+    }
+}
+
+pub fn kind_to_token(kind: &CupKind) -> Option<String> {
+    match kind {
+        CupKind::Normal => None,
+        CupKind::OneWay => Some("oneway".to_string()),
+        CupKind::Locked(Lock::Pours(pours)) => Some(format!("locked:{}", pours)),
+        CupKind::Locked(Lock::Color(color)) => Some(format!("locked:{}", liquid_to_token(color))),
+    }
+}
+
+// The level file for a board, parse_level reads it back the same
+pub fn level_to_string(level: &Level) -> String {
+    let mut lines: Vec<String> = level
+        .cups
+        .iter()
+        .map(|cup| {
+            let mut words: Vec<String> = kind_to_token(&cup.kind).into_iter().collect();
+            if cup.hidden > 0 {
+                words.push(format!("hidden:{}", cup.hidden));
+            }
+            words.push(cup.capacity.to_string());
+            words.extend(cup.liquids.iter().map(liquid_to_token));
+            words.join(" ")
+        })
+        .collect();
+    if let Some(goal) = &level.goal {
+        lines.push(format!("goal {}", goal));
+    }
+    if let Some(rules) = &level.rules {
+        lines.push(format!("rules {}", rules));
+    }
+    lines.join("\n") + "\n"
+}
+
+// A level squeezed onto one line that can be pasted into a shell,
+// cups are split by / and words by .
+pub fn share_code(level: &Level) -> String {
+    level_to_string(level)
+        .trim_end()
+        .replace('\n', "/")
+        .replace(' ', ".")
+}
+
+pub fn parse_share_code(code: &str) -> Result<Level, String> {
+    parse_level(&code.trim().replace('/', "\n").replace('.', " "))
+}
+
+pub fn parse_level(text: &str) -> Result<Level, String> {
+    let mut level = Level::default();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        let first = words.next().unwrap_or_default();
+        match first {
+            "goal" => level.goal = words.next().map(|w| w.to_string()),
+            "rules" => level.rules = words.next().map(|w| w.to_string()),
+            _ => {
+                // The cup's type and hidden units come before its capacity
+                let mut kind = None;
+                let mut hidden = 0;
+                let mut first = first;
+                loop {
+                    if let Some(k) = kind_from_token(first) {
+                        kind = Some(k);
+                    } else if let Some(n) = first.strip_prefix("hidden:").and_then(|n| n.parse().ok()) {
+                        hidden = n;
+                    } else {
+                        break;
+                    }
+                    first = words.next().unwrap_or_default();
+                }
+                let capacity: usize = first
+                    .parse()
+                    .map_err(|_| format!("Line {}: a cup starts with its capacity", line_no + 1))?;
+                let liquids = words
+                    .map(|w| {
+                        liquid_from_token(w)
+                            .ok_or(format!("Line {}: unknown liquid '{}'", line_no + 1, w))
+                    })
+                    .collect::<Result<Vec<LiquidColors>, String>>()?;
+                if liquids.len() > capacity {
+                    return Err(format!("Line {}: the cup is overfilled", line_no + 1));
+                }
+                if hidden > 0 && hidden >= liquids.len() {
+                    return Err(format!("Line {}: the top unit can't be hidden", line_no + 1));
+                }
+                level.cups.push(Cup {
+                    capacity,
+                    liquids,
+                    hidden,
+                    kind: kind.unwrap_or_default(),
+                });
+            }
+        }
+    }
+    if level.cups.is_empty() {
+        return Err("A level needs at least one cup".to_string());
+    }
+    Ok(level)
+}
+
+pub fn load_level(path: &str) -> Result<Level, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    parse_level(&text)
+}
+
+// Saved levels go in a levels folder next to the stats
+pub fn save_level(level: &Level) -> Result<PathBuf, String> {
+    save_to_data_dir("levels", "level", &level_to_string(level))
+}
+
+#[cfg(test)]
+mod test_level {
+    use super::*;
+
+    #[test]
+    fn parses_cups_and_options() {
+        let level = parse_level(
+            "# a comment\n4 red ice:green:3 syrup\n\noneway 4 shot\nlocked:5 2\ngoal full\nrules single\n",
+        )
+        .unwrap();
+        assert_eq!(level.cups.len(), 3);
+        assert_eq!(
+            level.cups[0].liquids,
+            vec![
+                LiquidColors::Red,
+                LiquidColors::Ice(Box::new(LiquidColors::Green), 3),
+                LiquidColors::Syrup
+            ]
+        );
+        assert_eq!(level.cups[1].kind, CupKind::OneWay);
+        assert_eq!(level.cups[2].capacity, 2);
+        assert_eq!(level.cups[2].kind, CupKind::Locked(Lock::Pours(5)));
+        assert_eq!(level.goal.as_deref(), Some("full"));
+        assert_eq!(level.rules.as_deref(), Some("single"));
+
+        let by_color = parse_level("4 red red\nlocked:red 4").unwrap();
+        assert_eq!(by_color.cups[1].kind, CupKind::Locked(Lock::Color(LiquidColors::Red)));
+    }
+
+    #[test]
+    fn levels_round_trip() {
+        let text = "4 red ice:green:3 syrup\noneway 4 shot\nlocked:blue 2\ngoal full\n";
+        let level = parse_level(text).unwrap();
+        assert_eq!(level_to_string(&level), text);
+
+        let code = share_code(&level);
+        assert_eq!(code, "4.red.ice:green:3.syrup/oneway.4.shot/locked:blue.2/goal.full");
+        assert_eq!(level_to_string(&parse_share_code(&code).unwrap()), text);
+
+        let mystery = parse_level("oneway hidden:2 4 red green red\n").unwrap();
+        assert_eq!(mystery.cups[0].hidden, 2);
+        assert_eq!(mystery.cups[0].kind, CupKind::OneWay);
+        assert_eq!(level_to_string(&mystery), "oneway hidden:2 4 red green red\n");
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert!(parse_level("2 red red red").is_err());
+        assert!(parse_level("4 purple").is_err());
+        assert!(parse_level("4 ice:shot:2").is_err());
+        assert!(parse_level("red").is_err());
+        assert!(parse_level("locked:0 4").is_err());
+        assert!(parse_level("locked:syrup 4").is_err());
+        assert!(parse_level("# nothing").is_err());
+        assert!(parse_level("hidden:2 4 red green").is_err());
+    }
+}
@@ -1,22 +1,97 @@
-use crate::cup::{Cup, scramble_cups};
+use crate::cup::{scramble_cups_steps, Cup, CupKind, Lock};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use crate::liquids::LiquidColors;
+use crate::solver::solve_within;
+use crate::pour_rule::Standard;
+use crate::win_condition::UniqueMonochrome;
+use std::{
+    fmt,
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
 
 const CAPACITY: usize = 5;
+// Cup sizes for boards that mix them, a color always fills a mug
+const ESPRESSO_CAPACITY: usize = 3;
+const PINT_CAPACITY: usize = 7;
 
+// Colors in the order boards start using them
+const PALETTE: [LiquidColors; 8] = [
+    LiquidColors::Red,
+    LiquidColors::Green,
+    LiquidColors::Blue,
+    LiquidColors::BabyBlue,
+    LiquidColors::Pink,
+    LiquidColors::Yellow,
+    LiquidColors::Brown,
+    LiquidColors::White,
+];
+// Boards the solver may expand while checking a generated board, the
+// generator moves on to another scramble rather than wait on one
+const GENERATOR_SEARCH_LIMIT: usize = 5_000;
+// Boards with an extra empty cup tried once the time budget runs out
+const FALLBACK_ATTEMPTS: u64 = 4;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum SimpleDifficulties {
     Easy,
     Medium,
+    #[default]
     Hard,
 }
 
+impl fmt::Display for SimpleDifficulties {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            SimpleDifficulties::Easy => "easy",
+            SimpleDifficulties::Medium => "medium",
+            SimpleDifficulties::Hard => "hard",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl SimpleDifficulties {
+    pub const ALL: [SimpleDifficulties; 3] = [
+        SimpleDifficulties::Easy,
+        SimpleDifficulties::Medium,
+        SimpleDifficulties::Hard,
+    ];
+
+    pub fn harder(self) -> SimpleDifficulties {
+        match self {
+            SimpleDifficulties::Easy => SimpleDifficulties::Medium,
+            _ => SimpleDifficulties::Hard,
+        }
+    }
+
+    pub fn easier(self) -> SimpleDifficulties {
+        match self {
+            SimpleDifficulties::Hard => SimpleDifficulties::Medium,
+            _ => SimpleDifficulties::Easy,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SimpleDifficulties> {
+        match name {
+            "easy" => Some(SimpleDifficulties::Easy),
+            "medium" => Some(SimpleDifficulties::Medium),
+            "hard" => Some(SimpleDifficulties::Hard),
+            _ => None,
+        }
+    }
+}
+
 pub fn get_full_cup(color: LiquidColors) -> Cup {
     let mut liquids = vec![];
-    for i in 0..CAPACITY {
+    for _ in 0..CAPACITY {
         liquids.push(color.clone());
     }
-    return Cup {
+    Cup {
         capacity: CAPACITY,
-        liquids: liquids,
+        liquids,
+        hidden: 0,
+        kind: CupKind::Normal,
     }
 }
 
@@ -25,13 +100,14 @@ fn generate_easy_cups() -> Vec<Cup> {
         get_full_cup(LiquidColors::Red),
         get_full_cup(LiquidColors::Green)
     ];
-    scramble_cups(cups.clone());
     cups.push(Cup {
         capacity: CAPACITY,
         liquids: vec![],
+        hidden: 0,
+        kind: CupKind::Normal,
     });
 
-    return cups;
+    cups
 }
 
 fn generate_medium_cups() -> Vec<Cup> {
@@ -43,18 +119,21 @@ fn generate_medium_cups() -> Vec<Cup> {
         get_full_cup(LiquidColors::Pink),
         get_full_cup(LiquidColors::Yellow),
     ];
-    scramble_cups(cups.clone());
     cups.push(Cup {
         capacity: CAPACITY,
         liquids: vec![],
+        hidden: 0,
+        kind: CupKind::Normal,
     });
 
     cups.push(Cup {
         capacity: CAPACITY,
         liquids: vec![],
+        hidden: 0,
+        kind: CupKind::Normal,
     });
 
-    return cups;
+    cups
 }
 
 fn generate_hard_cups() -> Vec<Cup> {
@@ -65,32 +144,296 @@ fn generate_hard_cups() -> Vec<Cup> {
         get_full_cup(LiquidColors::BabyBlue),
         get_full_cup(LiquidColors::Pink),
         get_full_cup(LiquidColors::Yellow),
-        get_full_cup(LiquidColors::Green),
-        get_full_cup(LiquidColors::Pink),
     ];
 
-    scramble_cups(cups.clone());
     cups.push(Cup {
         capacity: CAPACITY,
         liquids: vec![],
+        hidden: 0,
+        kind: CupKind::Normal,
     });
 
     cups.push(Cup {
         capacity: CAPACITY,
         liquids: vec![],
+        hidden: 0,
+        kind: CupKind::Normal,
     });
 
-    return cups;
+    cups
+}
 
+// Mystery boards only show the top unit of each cup
+pub fn hide_layers(cups: Vec<Cup>) -> Vec<Cup> {
+    cups.into_iter()
+        .map(|c| Cup {
+            hidden: c.liquids.len().saturating_sub(1),
+            ..c
+        })
+        .collect()
 }
 
 pub fn generate_cups(diff: SimpleDifficulties) -> Vec<Cup> {
     // Easy: 3 cups, 1 empty, 2 colors
     // Medium: 6 cups, 2 empty, 4 colors
     // Hard: 8 cups, 2 empty, 6 colors
-    return match diff {
+    match diff {
         SimpleDifficulties::Easy => generate_easy_cups(),
         SimpleDifficulties::Medium => generate_medium_cups(),
         SimpleDifficulties::Hard => generate_hard_cups()
     }
 }
+
+// Knobs for building a board from scratch instead of a fixed difficulty
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardParams {
+    pub colors: usize,
+    pub spare_cups: usize,
+    // Single unit pours used to mix the colors up
+    pub scramble_steps: usize,
+    // Special liquids mixed in once the board is scrambled
+    pub syrups: usize,
+    pub ice_blocks: usize,
+    pub shots: usize,
+    // Some colors come in pint glasses and a spare cup is an espresso cup
+    pub mixed_sizes: bool,
+    // Spare cups that only take pours, and spare cups that start locked
+    pub one_way_cups: usize,
+    pub locked_cups: usize,
+}
+
+impl BoardParams {
+    // Every board cleared in an endless run adds more mixing, a new
+    // color every other board, and eventually takes away a spare cup
+    pub fn endless(depth: usize) -> BoardParams {
+        BoardParams {
+            colors: (3 + depth / 2).min(PALETTE.len()),
+            spare_cups: if depth >= 12 { 1 } else { 2 },
+            scramble_steps: (20 + depth * 20).min(10_000),
+            ice_blocks: if depth >= 4 { (1 + (depth - 4) / 6).min(3) } else { 0 },
+            syrups: if depth >= 8 { 1 } else { 0 },
+            shots: if depth >= 14 { 1 } else { 0 },
+            mixed_sizes: depth >= 6,
+            locked_cups: if depth >= 10 { 1 } else { 0 },
+            one_way_cups: if depth >= 16 { 1 } else { 0 },
+        }
+    }
+
+    // Pours the best solution should take, growing by one a board until
+    // it reaches about as long as a full palette allows
+    pub fn endless_pars(depth: usize) -> RangeInclusive<usize> {
+        let fewest = (4 + depth).min(16);
+        fewest..=fewest + 4
+    }
+
+    // The closest fixed difficulty, used when recording stats
+    pub fn difficulty(&self) -> SimpleDifficulties {
+        match self.colors {
+            0..=3 => SimpleDifficulties::Easy,
+            4..=6 => SimpleDifficulties::Medium,
+            _ => SimpleDifficulties::Hard,
+        }
+    }
+}
+
+pub fn generate_board(params: BoardParams, seed: u64) -> Vec<Cup> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut cups: Vec<Cup> = PALETTE
+        .iter()
+        .take(params.colors)
+        .map(|color| get_full_cup(color.clone()))
+        .collect();
+    if params.mixed_sizes {
+        for cup in cups.iter_mut().skip(2).step_by(3) {
+            cup.capacity = PINT_CAPACITY;
+        }
+    }
+    for i in 0..params.spare_cups {
+        let capacity = match i {
+            0 if params.mixed_sizes => ESPRESSO_CAPACITY,
+            _ => CAPACITY,
+        };
+        // The last spare cups start locked, the scramble can't touch
+        // them so they are still empty when they open. Half of them
+        // wait for a color to be sorted instead of counting pours.
+        let kind = if i + params.locked_cups < params.spare_cups {
+            CupKind::Normal
+        } else if rng.random_bool(0.5) {
+            CupKind::Locked(Lock::Color(PALETTE[rng.random_range(0..params.colors)].clone()))
+        } else {
+            CupKind::Locked(Lock::Pours(rng.random_range(4..=10)))
+        };
+        cups.push(Cup {
+            capacity,
+            liquids: vec![],
+            hidden: 0,
+            kind,
+        });
+    }
+    let cups = scramble_cups_steps(cups, seed, params.scramble_steps);
+    let cups = add_specials(cups, params, seed);
+    add_one_way_cups(cups, params)
+}
+
+// Nothing comes back out of a one-way cup, so only a cup holding a
+// single color (or nothing) can become one
+fn add_one_way_cups(mut cups: Vec<Cup>, params: BoardParams) -> Vec<Cup> {
+    for _ in 0..params.one_way_cups {
+        let candidate = (0..cups.len())
+            .filter(|i| cups[*i].kind == CupKind::Normal)
+            .filter(|i| cups[*i].liquids.iter().all(|l| *l == cups[*i].liquids[0]))
+            .min_by_key(|i| cups[*i].liquids.len());
+        if let Some(i) = candidate {
+            cups[i].kind = CupKind::OneWay;
+        }
+    }
+    cups
+}
+
+// A random ordinary unit as (cup, position in the cup)
+fn pick_unit(cups: &[Cup], rng: &mut StdRng) -> Option<(usize, usize)> {
+    let units: Vec<(usize, usize)> = cups
+        .iter()
+        .enumerate()
+        .flat_map(|(i, c)| (0..c.liquids.len()).map(move |k| (i, k)))
+        .filter(|(i, k)| !cups[*i].liquids[*k].is_special())
+        .collect();
+    if units.is_empty() {
+        return None;
+    }
+    Some(units[rng.random_range(0..units.len())])
+}
+
+// Syrup and ice take the place of a unit that is already on the board,
+// so the same liquid is still there to sort. Shots go on top of a cup
+// that has room for them.
+fn add_specials(mut cups: Vec<Cup>, params: BoardParams, seed: u64) -> Vec<Cup> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..params.syrups {
+        if let Some((i, k)) = pick_unit(&cups, &mut rng) {
+            cups[i].liquids[k] = LiquidColors::Syrup;
+        }
+    }
+    for _ in 0..params.ice_blocks {
+        if let Some((i, k)) = pick_unit(&cups, &mut rng) {
+            let color = cups[i].liquids[k].clone();
+            cups[i].liquids[k] = LiquidColors::Ice(Box::new(color), rng.random_range(3..=8));
+        }
+    }
+    for _ in 0..params.shots {
+        let open: Vec<usize> = (0..cups.len())
+            .filter(|i| !cups[*i].liquids.is_empty() && cups[*i].liquids.len() < cups[*i].capacity)
+            .collect();
+        if !open.is_empty() {
+            let i = open[rng.random_range(0..open.len())];
+            cups[i].liquids.push(LiquidColors::Shot);
+        }
+    }
+    cups
+}
+
+// Tries scrambles until one needs a number of pours in the given range,
+// mixing more when boards come out too short and less when they come
+// out too long. Once the budget runs out it settles for the closest
+// board found, or a board with an extra spare cup if none of them
+// could be solved. Boards that come out already sorted are never used.
+// Returns the board and its par. The budget is wall-clock time, so on a
+// slower machine fewer scrambles get tried and the same seed can give a
+// different board.
+pub fn generate_board_with_par(
+    params: BoardParams,
+    pars: RangeInclusive<usize>,
+    budget: Duration,
+    seed: u64,
+) -> (Vec<Cup>, Option<usize>) {
+    let started = Instant::now();
+    let mut tuned = params;
+    let mut closest: Option<(usize, Vec<Cup>, usize)> = None;
+    let mut attempt = 0;
+    while started.elapsed() < budget {
+        let cups = generate_board(tuned, seed.wrapping_add(attempt));
+        attempt += 1;
+        let solution = solve_within(&cups, &UniqueMonochrome, &Standard, GENERATOR_SEARCH_LIMIT);
+        let Some(par) = solution.map(|pours| pours.len()) else {
+            continue;
+        };
+        // Already sorted, mix more and try again
+        if par == 0 {
+            tuned.scramble_steps = tuned.scramble_steps * 3 / 2 + 1;
+            continue;
+        }
+        if pars.contains(&par) {
+            return (cups, Some(par));
+        }
+        let too_short = par < *pars.start();
+        let distance = if too_short { pars.start() - par } else { par - pars.end() };
+        if closest.as_ref().is_none_or(|(d, _, _)| distance < *d) {
+            closest = Some((distance, cups, par));
+        }
+        tuned.scramble_steps = match too_short {
+            true => tuned.scramble_steps * 3 / 2 + 1,
+            false => (tuned.scramble_steps * 2 / 3).max(1),
+        };
+    }
+    match closest {
+        Some((_, cups, par)) => (cups, Some(par)),
+        None => {
+            let easier = BoardParams {
+                spare_cups: params.spare_cups + 1,
+                ..params
+            };
+            for attempt in 0..FALLBACK_ATTEMPTS {
+                let cups = generate_board(easier, seed.wrapping_add(attempt));
+                let solution = solve_within(&cups, &UniqueMonochrome, &Standard, GENERATOR_SEARCH_LIMIT);
+                if let Some(pours) = solution.filter(|pours| !pours.is_empty()) {
+                    return (cups, Some(pours.len()));
+                }
+            }
+            // Still playable, just without a par to aim for
+            (generate_board(easier, seed), None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_level_creator {
+    use super::*;
+    use crate::solver::solve;
+
+    #[test]
+    fn boards_keep_every_unit() {
+        let params = BoardParams {
+            mixed_sizes: true,
+            locked_cups: 1,
+            ..BoardParams::endless(6)
+        };
+        let cups = generate_board(params, 7);
+        let units: usize = cups.iter().map(|c| c.liquids.len()).sum();
+        assert_eq!(units, params.colors * CAPACITY);
+        assert!(cups.iter().any(|c| c.capacity == PINT_CAPACITY));
+        assert!(cups.iter().all(|c| c.liquids.len() <= c.capacity));
+        assert!(cups.iter().any(|c| matches!(c.kind, CupKind::Locked(_))));
+    }
+
+    #[test]
+    fn boards_land_in_the_par_range() {
+        let params = BoardParams::endless(0);
+        let (cups, par) = generate_board_with_par(params, 5..=6, Duration::from_secs(10), 1);
+        assert!(par.is_some_and(|p| (5..=6).contains(&p)), "par {:?}", par);
+        assert_eq!(solve(&cups, &UniqueMonochrome, &Standard).map(|p| p.len()), par);
+
+        // Without any time the board still comes out solvable
+        let (_, par) = generate_board_with_par(params, 50..=60, Duration::ZERO, 1);
+        assert!(par.is_some());
+    }
+
+    #[test]
+    fn sorted_boards_are_never_dealt() {
+        let params = BoardParams {
+            scramble_steps: 0,
+            ..BoardParams::endless(0)
+        };
+        let (_, par) = generate_board_with_par(params, 0..=3, Duration::from_secs(10), 1);
+        assert!(par.is_some_and(|p| p > 0), "par {:?}", par);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod cup;
 pub mod liquids;
+pub mod render;
@@ -1,7 +1,7 @@
 use ratatui::style::Color;
 use std::fmt;
 
-#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 pub enum LiquidColors {
     Red,
     Green,
@@ -9,6 +9,15 @@ pub enum LiquidColors {
     Pink,
     BabyBlue,
     Yellow,
+    Brown,
+    White,
+    // Matches any color
+    Syrup,
+    // A frozen unit of a color, it can't be poured and nothing can be
+    // poured onto it until it melts after the given number of moves
+    Ice(Box<LiquidColors>, usize),
+    // Empties whatever cup it is poured onto
+    Shot,
 }
 
 impl fmt::Display for LiquidColors {
@@ -20,6 +29,11 @@ impl fmt::Display for LiquidColors {
             LiquidColors::Pink => "Pink",
             LiquidColors::BabyBlue => "BabyBlue",
             LiquidColors::Yellow=> "Yellow",
+            LiquidColors::Brown => "Brown",
+            LiquidColors::White => "White",
+            LiquidColors::Syrup => "Syrup",
+            LiquidColors::Ice(color, moves) => return write!(f, "Ice({}, {})", color, moves),
+            LiquidColors::Shot => "Shot",
         };
         write!(f, "{}", s)
     }
@@ -34,6 +48,28 @@ impl LiquidColors {
             LiquidColors::Pink => Color::LightMagenta,
             LiquidColors::BabyBlue => Color::LightBlue,
             LiquidColors::Yellow => Color::Yellow,
+            LiquidColors::Brown => Color::Indexed(94),
+            LiquidColors::White => Color::White,
+            LiquidColors::Syrup => Color::Magenta,
+            LiquidColors::Ice(color, _) => color.to_color(),
+            LiquidColors::Shot => Color::Black,
+        }
+    }
+
+    pub fn is_special(&self) -> bool {
+        matches!(
+            self,
+            LiquidColors::Syrup | LiquidColors::Ice(..) | LiquidColors::Shot
+        )
+    }
+
+    // Syrup goes with any color, ice and shots go with nothing
+    pub fn matches(&self, other: &LiquidColors) -> bool {
+        match (self, other) {
+            (LiquidColors::Ice(..) | LiquidColors::Shot, _) => false,
+            (_, LiquidColors::Ice(..) | LiquidColors::Shot) => false,
+            (LiquidColors::Syrup, _) | (_, LiquidColors::Syrup) => true,
+            (a, b) => a == b,
         }
     }
 }
@@ -1,10 +1,29 @@
 mod app;
+mod autoplay;
+mod cast;
 mod cup;
+mod daily;
+mod difficulty;
+mod editor;
+mod headless;
+mod highscores;
+mod level;
 mod liquids;
 mod level_creator;
+mod orders;
+mod player;
+mod pour_rule;
+mod render;
+mod replay;
+mod solver;
+mod speed;
+mod state_graph;
+mod stats;
+mod storage;
 mod ui;
+mod win_condition;
 
-use std::{error::Error, io};
+use std::{env, error::Error, fs, io, path::Path, time::Duration};
 
 use ratatui::{
     backend::{Backend, CrosstermBackend},
@@ -16,11 +35,21 @@ use ratatui::{
     Terminal,
 };
 
-use cup::{pour_a_into_b, scramble_cups};
+use cast::cast_replay;
 use level_creator::{generate_cups, SimpleDifficulties};
+use headless::{run_headless, Format};
+use highscores::MAX_NAME_LENGTH;
+use level::{load_level, parse_share_code};
+use player::simulate;
+use pour_rule::rule_from_name;
+use render::{format_from_name, render_board, RenderFormat};
+use replay::load_replay;
+use solver::explore_to_depth;
+use state_graph::{graph_format_for_path, StateGraph};
+use win_condition::goal_from_name;
 
 use crate::{
-    app::{App, CurrentScreen},
+    app::{App, CurrentScreen, GameMode, MENU_ITEMS},
     ui::ui,
 };
 
@@ -32,71 +61,461 @@ fn _main_helper() -> Result<(), String> {
     Ok(())
 }
 
+// Keys 1-9 pick the first nine cups and 0 picks the tenth
+fn cup_index_for_key(c: char) -> Option<u32> {
+    match c.to_digit(10)? {
+        0 => Some(9),
+        d => Some(d - 1),
+    }
+}
+
+// How often the screen is redrawn while waiting for a key,
+// keeps the timed mode's clock moving
+const TICK_RATE: Duration = Duration::from_millis(250);
+
 fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<bool> {
     loop {
+        app.tick();
         terminal.draw(|f| ui(f, app))?;
+        // Wakes up in time for the solver's next step while it plays
+        let next_step = match (&app.autoplay, &app.playback) {
+            (Some(autoplay), _) => autoplay.until_next_step(),
+            (None, Some(playback)) => playback.until_next_step(),
+            (None, None) => None,
+        };
+        let timeout = next_step.map_or(TICK_RATE, |t| t.min(TICK_RATE));
+        if !event::poll(timeout)? {
+            continue;
+        }
         if let Event::Key(key) = event::read()? {
             if key.kind == event::KeyEventKind::Release {
                 // Skip key release events
                 continue;
             }
+            // While typing a name every key goes into the name
+            if let Some(name) = app.name_entry.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) if name.chars().count() < MAX_NAME_LENGTH => name.push(c),
+                    KeyCode::Backspace => {
+                        name.pop();
+                    }
+                    KeyCode::Enter => app.submit_name(),
+                    KeyCode::Esc => app.name_entry = None,
+                    _ => {}
+                }
+                continue;
+            }
             if key.code == KeyCode::Char('q') {
+                app.quit();
                 return Ok(true);
             }
+            // A replay only has keys for watching it
+            if let Some(playback) = app.playback.as_mut() {
+                match key.code {
+                    KeyCode::Char(' ') => playback.paused = !playback.paused,
+                    KeyCode::Left => {
+                        playback.paused = true;
+                        playback.seek(playback.position.saturating_sub(1));
+                    }
+                    KeyCode::Right => {
+                        playback.paused = true;
+                        playback.seek(playback.position + 1);
+                    }
+                    KeyCode::Home => playback.seek(0),
+                    KeyCode::End => playback.seek(usize::MAX),
+                    KeyCode::Char('+') | KeyCode::Char('=') => playback.speed.faster(),
+                    KeyCode::Char('-') => playback.speed.slower(),
+                    _ => {}
+                }
+                app.show_playback();
+                continue;
+            }
+            // While the solver plays a few keys control it, and any
+            // other key takes over from it
+            if app.current_screen == CurrentScreen::Main
+                && let Some(autoplay) = app.autoplay.as_mut()
+            {
+                match key.code {
+                    KeyCode::Char(' ') => autoplay.paused = !autoplay.paused,
+                    KeyCode::Char('n') => {
+                        autoplay.paused = true;
+                        app.step_autoplay();
+                    }
+                    KeyCode::Char('+') | KeyCode::Char('=') => autoplay.speed.faster(),
+                    KeyCode::Char('-') => autoplay.speed.slower(),
+                    _ => app.stop_autoplay(),
+                }
+                if app.autoplay.is_some() {
+                    continue;
+                }
+            }
             match app.current_screen {
+                CurrentScreen::Welcome => match key.code {
+                    KeyCode::Up => app.menu_selection = app.menu_selection.saturating_sub(1),
+                    KeyCode::Down => {
+                        app.menu_selection = (app.menu_selection + 1).min(MENU_ITEMS.len() - 1)
+                    }
+                    KeyCode::Left => app.difficulty = app.difficulty.easier(),
+                    KeyCode::Right => app.difficulty = app.difficulty.harder(),
+                    KeyCode::Enter => *app = app.start(MENU_ITEMS[app.menu_selection]),
+                    KeyCode::Char('s') => app.open_overlay(CurrentScreen::Statistics),
+                    _ => {}
+                },
                 CurrentScreen::Main => match key.code {
+                    KeyCode::Char('s') => app.open_overlay(CurrentScreen::Statistics),
+                    KeyCode::Char('l') => app.open_overlay(CurrentScreen::HighScores),
+                    KeyCode::Char('h') => {
+                        app.hint();
+                    }
+                    KeyCode::Char('d') => app.check_dead_end(),
+                    KeyCode::Char('g') if app.mode == GameMode::Endless => app.end_run(),
+                    KeyCode::Char('a') => app.start_autoplay(),
+                    KeyCode::Enter if app.mode == GameMode::Orders => {
+                        app.tooltip = Some(app.serve().unwrap_or_else(|e| e));
+                    }
+                    KeyCode::Char('x') if app.mode == GameMode::Orders => {
+                        app.tooltip = app.dump().err();
+                    }
+                    KeyCode::Char('u') => {
+                        app.tooltip = app.undo().err().map(|e| e.to_string());
+                    }
                     KeyCode::Char(c) if c.is_ascii_digit() => {
                         app.tooltip = None;
                         if app.src_selection.is_none() {
-                            let raw_src_sel = c.to_digit(10);
-                            if matches!(raw_src_sel, None | Some(0)) {
-                                continue;
-                            }
-                            app.src_selection = Some(raw_src_sel.unwrap() - 1);
+                            app.src_selection = cup_index_for_key(c);
                         } else {
-                            let raw_dst_sel = c.to_digit(10);
-                            if matches!(raw_dst_sel, None | Some(0)) {
-                                continue;
-                            }
-                            let dst_sel = (raw_dst_sel.unwrap() - 1) as usize;
-                            let src_sel = app.src_selection.unwrap() as usize;
-                            app.src_selection = None;
-                            if src_sel >= app.cups.len() || dst_sel >= app.cups.len() {
+                            let Some(dst_sel) = cup_index_for_key(c) else {
                                 continue;
-                            }
-                            let r = pour_a_into_b(&app.cups[src_sel], &app.cups[dst_sel]);
-                            match r {
-                                Ok((new_src, new_dst)) => {
-                                    app.cups[src_sel] = new_src;
-                                    app.cups[dst_sel] = new_dst;
-                                }
-                                Err(msg) => app.tooltip = Some(msg),
-                            }
-                            if cup::are_cups_solved(&app.cups) {
-                                app.current_screen = CurrentScreen::Finished;
-                            }
+                            };
+                            let src_sel = app.src_selection.take().unwrap();
+                            app.tooltip = app.pour(src_sel as usize, dst_sel as usize).err();
                         }
                     }
                     _ => {}
                 },
-                CurrentScreen::Finished => {
-                    if key.code == KeyCode::Enter {
+                CurrentScreen::Finished => match key.code {
+                    KeyCode::Enter => *app = app.next_game(),
+                    KeyCode::Char('g') if app.mode == GameMode::Endless => app.end_run(),
+                    KeyCode::Char('s') => app.open_overlay(CurrentScreen::Statistics),
+                    KeyCode::Char('l') => app.open_overlay(CurrentScreen::HighScores),
+                    _ => {}
+                },
+                CurrentScreen::Stuck => match key.code {
+                    KeyCode::Char('u') => {
+                        let _ = app.undo();
+                    }
+                    KeyCode::Char('r') => app.restart(),
+                    KeyCode::Esc => {
+                        app.abandon();
+                        *app = App::new();
+                    }
+                    _ => {}
+                },
+                CurrentScreen::GameOver => match (app.mode, key.code) {
+                    (GameMode::Timed, KeyCode::Enter) => {
+                        *app = App::timed(app.difficulty, Duration::ZERO, 0)
+                    }
+                    (GameMode::Limited, KeyCode::Char('u')) => {
+                        let _ = app.undo();
+                    }
+                    (GameMode::Limited, KeyCode::Char('r')) => app.restart(),
+                    (GameMode::Limited, KeyCode::Enter) => {
+                        app.abandon();
+                        *app = App::limited(app.difficulty);
+                    }
+                    (GameMode::Endless, KeyCode::Enter) => *app = App::endless(0, 0),
+                    (_, KeyCode::Esc) => {
+                        app.abandon();
                         *app = App::new();
                     }
+                    _ => {}
+                },
+                CurrentScreen::Editor => {
+                    let Some(editor) = app.editor.as_mut() else {
+                        continue;
+                    };
+                    app.tooltip = None;
+                    if key.code != KeyCode::Esc {
+                        editor.leaving = false;
+                    }
+                    match key.code {
+                        KeyCode::Left => editor.move_cursor(&app.cups, false),
+                        KeyCode::Right => editor.move_cursor(&app.cups, true),
+                        KeyCode::Up => editor.cycle_paint(false),
+                        KeyCode::Down => editor.cycle_paint(true),
+                        KeyCode::Char(c) if c.is_ascii_digit() => {
+                            if let Some(idx) = cup_index_for_key(c) {
+                                app.tooltip = editor.select(&app.cups, idx as usize).err();
+                            }
+                        }
+                        KeyCode::Enter | KeyCode::Char(' ') => {
+                            app.tooltip = editor.paint(&mut app.cups).err();
+                        }
+                        KeyCode::Backspace => app.tooltip = editor.erase(&mut app.cups).err(),
+                        KeyCode::Char('a') => app.tooltip = editor.add_cup(&mut app.cups).err(),
+                        KeyCode::Char('d') => app.tooltip = editor.remove_cup(&mut app.cups).err(),
+                        KeyCode::Char('+') | KeyCode::Char('=') => {
+                            app.tooltip = editor.resize(&mut app.cups, true).err();
+                        }
+                        KeyCode::Char('-') => {
+                            app.tooltip = editor.resize(&mut app.cups, false).err();
+                        }
+                        KeyCode::Char('i') => app.tooltip = editor.toggle_ice(&mut app.cups).err(),
+                        KeyCode::Char('t') => editor.cycle_kind(&mut app.cups),
+                        KeyCode::Char('v') => app.check_level(),
+                        KeyCode::Char('w') => app.save_level(),
+                        KeyCode::Char('c') => app.share_level(),
+                        KeyCode::Char('p') => match app.play_level() {
+                            Ok(next) => *app = next,
+                            Err(e) => app.tooltip = Some(e),
+                        },
+                        KeyCode::Esc if editor.can_leave(&app.cups) => *app = App::new(),
+                        KeyCode::Esc => {
+                            app.tooltip = Some(
+                                "This board isn't saved, Esc again leaves without it".to_string(),
+                            );
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::Statistics => {
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Char('s')) {
+                        app.close_overlay();
+                    }
+                }
+                CurrentScreen::HighScores => {
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Char('l')) {
+                        app.close_overlay();
+                    }
                 }
-                _ => {}
             }
         }
     }
 }
 
+// Prints level files from easiest to hardest
+fn rate_levels(paths: &[String]) -> Result<(), String> {
+    let mut rated = vec![];
+    for path in paths {
+        let app = App::from_level(load_level(path)?)?;
+        rated.push((app.rating(), path));
+    }
+    rated.sort_by(|a, b| a.0.score.total_cmp(&b.0.score));
+    for (rating, path) in rated {
+        println!("{}  {}", path, rating);
+    }
+    Ok(())
+}
+
+// Boards --graph stops at when --nodes doesn't say otherwise
+const GRAPH_NODE_LIMIT: usize = 10_000;
+
+// Games played per difficulty when --simulate isn't told how many
+const SIMULATED_GAMES: usize = 100;
+
+// Plays every difficulty with a computer player and prints how it did
+fn simulate_players(player: &str, games: usize) -> Result<(), String> {
+    for difficulty in [
+        SimpleDifficulties::Easy,
+        SimpleDifficulties::Medium,
+        SimpleDifficulties::Hard,
+    ] {
+        let summary = simulate(player, difficulty, games, 0)?;
+        println!("{:<8}{}", difficulty.to_string(), summary);
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    // --rate <files> rates level files without starting the game
+    if let Some(i) = args.iter().position(|a| a == "--rate") {
+        if args.len() <= i + 1 {
+            return Err("--rate needs at least one level file".into());
+        }
+        rate_levels(&args[i + 1..])?;
+        return Ok(());
+    }
+    // --simulate <player> [games] plays boards with a computer player
+    if let Some(i) = args.iter().position(|a| a == "--simulate") {
+        let player = args
+            .get(i + 1)
+            .ok_or("--simulate needs one of random, greedy or optimal")?;
+        let games = match args.get(i + 2) {
+            Some(games) => games.parse().map_err(|_| "--simulate needs a number of games")?,
+            None => SIMULATED_GAMES,
+        };
+        simulate_players(player, games)?;
+        return Ok(());
+    }
+    // render <level file|share code> [--format svg|html|ansi] prints
+    // the board as an image or colored text
+    if args.get(1).map(|a| a.as_str()) == Some("render") {
+        let board = args.get(2).ok_or("render needs a level file or share code")?;
+        let format = match args.iter().position(|a| a == "--format") {
+            Some(i) => args
+                .get(i + 1)
+                .and_then(|name| format_from_name(name))
+                .ok_or("--format needs one of svg, html or ansi")?,
+            None => RenderFormat::Svg,
+        };
+        let level = match Path::new(board).exists() {
+            true => load_level(board)?,
+            false => parse_share_code(board)?,
+        };
+        print!("{}", render_board(&level.cups, format));
+        return Ok(());
+    }
+    // --daily jumps straight into today's Daily Blend,
+    // --seed <n> replays a specific board, --goal <name> plays it
+    // towards a different goal, --rules <name> changes how
+    // liquid can be poured, --level <file> plays a level file and
+    // --code <code> plays a level shared from the editor
+    let seed = match args.iter().position(|a| a == "--seed") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .and_then(|s| s.parse::<u64>().ok())
+                .ok_or("--seed needs a number")?,
+        ),
+        None => None,
+    };
+    let goal = match args.iter().position(|a| a == "--goal") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .ok_or("--goal needs one of sort, full, layout or fill")?
+                .clone(),
+        ),
+        None => None,
+    };
+
+    let rule = match args.iter().position(|a| a == "--rules") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .and_then(|name| rule_from_name(name))
+                .ok_or("--rules needs one of standard, single, empty or sandbox")?,
+        ),
+        None => None,
+    };
+
+    let level = match args.iter().position(|a| a == "--level") {
+        Some(i) => Some(load_level(args.get(i + 1).ok_or("--level needs a file")?)?),
+        None => None,
+    };
+    let level = match args.iter().position(|a| a == "--code") {
+        Some(i) => Some(parse_share_code(args.get(i + 1).ok_or("--code needs a share code")?)?),
+        None => level,
+    };
+
+    // replay <file> watches a saved game
+    let replay = match args.get(1).map(|a| a.as_str()) {
+        Some("replay") => Some(load_replay(args.get(2).ok_or("replay needs a replay file")?)?),
+        _ => None,
+    };
+
+    let custom = goal.is_some() || rule.is_some();
+    // The Daily Blend is the same board for everyone, so it can't be
+    // played towards a different goal or with different rules
+    if custom && args.iter().any(|a| a == "--daily") {
+        return Err("--daily can't be combined with --goal or --rules".into());
+    }
+    let mut app = match (replay, level, seed) {
+        (Some(replay), _, _) => App::from_replay(replay)?,
+        (None, Some(level), _) => App::from_level(level)?,
+        (None, None, Some(seed)) => App::with_seed(SimpleDifficulties::Hard, seed),
+        (None, None, None) if custom => App::classic(SimpleDifficulties::Hard),
+        (None, None, None) if args.iter().any(|a| a == "--daily") => App::daily(),
+        (None, None, None) => App::new(),
+    };
+    if let Some(rule) = rule {
+        app = app.with_rule(rule);
+    }
+    if let Some(name) = goal {
+        let goal = goal_from_name(&name, &app.cups)
+            .ok_or("--goal needs one of sort, full, layout or fill")?;
+        app = app.with_goal(goal);
+    }
+    if custom {
+        app = app.solved();
+    }
+
+    // --autoplay starts with the solver playing the board
+    if args.iter().any(|a| a == "--autoplay") {
+        if app.current_screen == CurrentScreen::Welcome {
+            app = App::classic(app.difficulty);
+        }
+        app.start_autoplay();
+    }
+
+    // --cast <file> writes an asciinema recording of a replay, or of
+    // the solver sorting the board picked by the other options
+    if let Some(i) = args.iter().position(|a| a == "--cast") {
+        let path = args.get(i + 1).ok_or("--cast needs a file to write to")?;
+        if app.current_screen == CurrentScreen::Welcome {
+            app = App::classic(app.difficulty);
+        }
+        let replay = match &app.playback {
+            Some(playback) => playback.replay.clone(),
+            None => app.solution_replay()?,
+        };
+        let title = format!("terminal barista board #{}", replay.seed);
+        fs::write(path, cast_replay(replay, &title)?)?;
+        return Ok(());
+    }
+
+    // --graph <file> writes every board reachable from the board picked
+    // by the other options, as DOT or as JSON for a .json file.
+    // --depth <n> and --nodes <n> stop it early on big boards.
+    if let Some(i) = args.iter().position(|a| a == "--graph") {
+        let path = args.get(i + 1).ok_or("--graph needs a file to write to")?;
+        if app.current_screen == CurrentScreen::Welcome {
+            app = App::classic(app.difficulty);
+        }
+        let max_depth = match args.iter().position(|a| a == "--depth") {
+            Some(i) => args
+                .get(i + 1)
+                .and_then(|s| s.parse::<usize>().ok())
+                .ok_or("--depth needs a number")?,
+            None => usize::MAX,
+        };
+        let limit = match args.iter().position(|a| a == "--nodes") {
+            Some(i) => args
+                .get(i + 1)
+                .and_then(|s| s.parse::<usize>().ok())
+                .ok_or("--nodes needs a number")?,
+            None => GRAPH_NODE_LIMIT,
+        };
+        let space = explore_to_depth(
+            &app.cups,
+            app.goal.as_ref(),
+            app.rule.as_ref(),
+            limit,
+            max_depth,
+        );
+        let graph = StateGraph::new(space, app.goal.as_ref());
+        fs::write(path, graph.to_format(graph_format_for_path(path)))?;
+        println!("{}", graph);
+        return Ok(());
+    }
+
+    // --headless plays over stdin and stdout, --json answers in JSON
+    if args.iter().any(|a| a == "--headless") {
+        if app.current_screen == CurrentScreen::Welcome {
+            app = App::classic(app.difficulty);
+        }
+        let format = match args.iter().any(|a| a == "--json") {
+            true => Format::Json,
+            false => Format::Text,
+        };
+        run_headless(&mut app, format, io::stdin().lock(), io::stdout().lock())?;
+        app.quit();
+        return Ok(());
+    }
+
     enable_raw_mode()?;
     let mut stderr = io::stderr();
     execute!(stderr, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stderr);
     let mut terminal = Terminal::new(backend)?;
-    let mut app = App::new();
     let _res = run_app(&mut terminal, &mut app);
     disable_raw_mode()?;
     execute!(
@@ -106,5 +525,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     )?;
     terminal.show_cursor()?;
 
+    if let Some(share) = app.share {
+        println!("{}", share);
+    }
+
     Ok(())
 }
@@ -0,0 +1,267 @@
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::cup::{Cup, CupKind};
+use crate::liquids::LiquidColors;
+
+// What the taps behind the counter pour
+pub const TAPS: [LiquidColors; 4] = [
+    LiquidColors::Brown,
+    LiquidColors::White,
+    LiquidColors::Yellow,
+    LiquidColors::Green,
+];
+pub const SERVING_CUPS: usize = 3;
+// Cups are all drawn the same height, so taps match the serving cups
+const TAP_CAPACITY: usize = 4;
+const SERVING_CAPACITY: usize = 4;
+
+const CUSTOMERS_PER_SHIFT: usize = 8;
+const MAX_WAITING: usize = 3;
+const ARRIVAL_GAP: Duration = Duration::from_secs(12);
+// Bigger drinks take longer to make, so customers wait longer for them
+const BASE_PATIENCE: Duration = Duration::from_secs(40);
+const PATIENCE_PER_LAYER: Duration = Duration::from_secs(10);
+
+pub fn ingredient_name(liquid: &LiquidColors) -> &'static str {
+    match liquid {
+        LiquidColors::Brown => "espresso",
+        LiquidColors::White => "milk",
+        LiquidColors::Yellow => "caramel",
+        LiquidColors::Green => "matcha",
+        LiquidColors::Pink => "strawberry",
+        LiquidColors::Red => "raspberry",
+        LiquidColors::Blue => "blueberry",
+        LiquidColors::BabyBlue => "vanilla",
+        LiquidColors::Syrup => "syrup",
+        LiquidColors::Ice(..) => "ice",
+        LiquidColors::Shot => "a shot",
+    }
+}
+
+// The first cups on an order board are taps, followed by serving cups
+pub fn order_board() -> Vec<Cup> {
+    let mut cups: Vec<Cup> = TAPS
+        .iter()
+        .map(|color| Cup {
+            capacity: TAP_CAPACITY,
+            liquids: vec![color.clone(); TAP_CAPACITY],
+            hidden: 0,
+            kind: CupKind::Normal,
+        })
+        .collect();
+    for _ in 0..SERVING_CUPS {
+        cups.push(Cup {
+            capacity: SERVING_CAPACITY,
+            liquids: vec![],
+            hidden: 0,
+            kind: CupKind::Normal,
+        });
+    }
+    cups
+}
+
+pub fn is_tap(cup_idx: usize) -> bool {
+    cup_idx < TAPS.len()
+}
+
+#[derive(Debug, Clone)]
+pub struct Order {
+    pub customer: usize,
+    // Bottom layer first
+    pub recipe: Vec<LiquidColors>,
+    pub arrived_at: Instant,
+    pub patience: Duration,
+}
+
+impl Order {
+    pub fn patience_left(&self, now: Instant) -> Duration {
+        self.patience
+            .saturating_sub(now.saturating_duration_since(self.arrived_at))
+    }
+
+    // Groups repeated layers, e.g. "espresso, milk x2, caramel"
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<(String, usize)> = vec![];
+        for layer in self.recipe.iter() {
+            let name = ingredient_name(layer).to_string();
+            match parts.last_mut() {
+                Some((last, count)) if *last == name => *count += 1,
+                _ => parts.push((name, 1)),
+            }
+        }
+        parts
+            .iter()
+            .map(|(name, count)| match count {
+                1 => name.clone(),
+                n => format!("{} x{}", name, n),
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+// Share of the recipe's layers that are in the right place, a drink
+// with extra or missing layers loses marks for each one
+pub fn accuracy(drink: &[LiquidColors], recipe: &[LiquidColors]) -> f64 {
+    let layers = drink.len().max(recipe.len());
+    if layers == 0 {
+        return 0.0;
+    }
+    let correct = drink.iter().zip(recipe.iter()).filter(|(d, r)| d == r).count();
+    correct as f64 / layers as f64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Served {
+    pub customer: usize,
+    pub accuracy: f64,
+    pub points: usize,
+}
+
+#[derive(Debug)]
+pub struct Shift {
+    rng: StdRng,
+    pub waiting: Vec<Order>,
+    pub customers_arrived: usize,
+    pub next_arrival: Instant,
+    pub served: Vec<Served>,
+    pub walkouts: usize,
+}
+
+impl Shift {
+    pub fn new(seed: u64, now: Instant) -> Shift {
+        let mut shift = Shift {
+            rng: StdRng::seed_from_u64(seed),
+            waiting: vec![],
+            customers_arrived: 0,
+            next_arrival: now,
+            served: vec![],
+            walkouts: 0,
+        };
+        // Open with two customers at the counter
+        shift.arrive(now);
+        shift.arrive(now);
+        shift.next_arrival = now + ARRIVAL_GAP;
+        shift
+    }
+
+    fn arrive(&mut self, now: Instant) {
+        let layers = self.rng.random_range(2..=SERVING_CAPACITY);
+        let recipe = (0..layers)
+            .map(|_| TAPS[self.rng.random_range(0..TAPS.len())].clone())
+            .collect();
+        self.waiting.push(Order {
+            customer: self.customers_arrived + 1,
+            recipe,
+            arrived_at: now,
+            patience: BASE_PATIENCE + PATIENCE_PER_LAYER * layers as u32,
+        });
+        self.customers_arrived += 1;
+    }
+
+    // Lets impatient customers leave and new ones come in.
+    // Returns how many customers walked out.
+    pub fn tick(&mut self, now: Instant) -> usize {
+        let before = self.waiting.len();
+        self.waiting.retain(|o| !o.patience_left(now).is_zero());
+        let left = before - self.waiting.len();
+        self.walkouts += left;
+
+        while now >= self.next_arrival && self.customers_arrived < CUSTOMERS_PER_SHIFT {
+            if self.waiting.len() < MAX_WAITING {
+                self.arrive(now);
+            }
+            self.next_arrival += ARRIVAL_GAP;
+        }
+        // Nobody at the counter, so skip ahead to the next customer
+        if self.waiting.is_empty() && self.customers_arrived < CUSTOMERS_PER_SHIFT {
+            self.arrive(now);
+            self.next_arrival = now + ARRIVAL_GAP;
+        }
+        left
+    }
+
+    // Hands the drink to the waiting customer it matches best
+    pub fn serve(&mut self, drink: &[LiquidColors], now: Instant) -> Result<Served, String> {
+        if drink.is_empty() {
+            return Err("That cup is empty, nobody wants it".to_string());
+        }
+        let (idx, best) = self
+            .waiting
+            .iter()
+            .enumerate()
+            .map(|(i, o)| (i, accuracy(drink, &o.recipe)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .ok_or("Nobody is waiting for a drink")?;
+        let order = self.waiting.remove(idx);
+        let speed_bonus = order.patience_left(now).as_secs() as usize;
+        let served = Served {
+            customer: order.customer,
+            accuracy: best,
+            points: (best * 100.0).round() as usize + if best == 1.0 { speed_bonus } else { 0 },
+        };
+        self.served.push(served.clone());
+        Ok(served)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.customers_arrived == CUSTOMERS_PER_SHIFT && self.waiting.is_empty()
+    }
+
+    pub fn score(&self) -> usize {
+        self.served.iter().map(|s| s.points).sum()
+    }
+
+    pub fn perfect_drinks(&self) -> usize {
+        self.served.iter().filter(|s| s.accuracy == 1.0).count()
+    }
+}
+
+#[cfg(test)]
+mod test_orders {
+    use super::*;
+    use LiquidColors::{Brown, White, Yellow};
+
+    #[test]
+    fn accuracy_by_layer() {
+        assert_eq!(accuracy(&[Brown, White], &[Brown, White]), 1.0);
+        assert_eq!(accuracy(&[Brown, Yellow], &[Brown, White]), 0.5);
+        assert_eq!(accuracy(&[Brown], &[Brown, White, White, Yellow]), 0.25);
+        assert_eq!(accuracy(&[], &[Brown]), 0.0);
+    }
+
+    #[test]
+    fn describes_layers() {
+        let order = Order {
+            customer: 1,
+            recipe: vec![Brown, White, White, Yellow],
+            arrived_at: Instant::now(),
+            patience: BASE_PATIENCE,
+        };
+        assert_eq!(order.describe(), "espresso, milk x2, caramel");
+    }
+
+    #[test]
+    fn serves_best_match() {
+        let now = Instant::now();
+        let mut shift = Shift::new(1, now);
+        let wanted = shift.waiting[1].recipe.clone();
+        let served = shift.serve(&wanted, now).unwrap();
+        assert_eq!(served.accuracy, 1.0);
+        assert_eq!(shift.waiting.len(), 1);
+        assert!(shift.serve(&[], now).is_err());
+    }
+
+    #[test]
+    fn impatient_customers_leave() {
+        let now = Instant::now();
+        let mut shift = Shift::new(1, now);
+        let later = now + BASE_PATIENCE + PATIENCE_PER_LAYER * SERVING_CAPACITY as u32;
+        assert_eq!(shift.tick(later), 2);
+        assert_eq!(shift.walkouts, 2);
+        // The counter is never left empty while customers are still to come
+        assert!(!shift.waiting.is_empty());
+    }
+}
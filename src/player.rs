@@ -0,0 +1,215 @@
+use std::collections::HashSet;
+use std::fmt::{self, Debug};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::cup::{scramble_cups, settle, Cup};
+use crate::level_creator::{generate_cups, SimpleDifficulties};
+use crate::pour_rule::{PourRule, Standard};
+use crate::solver::{board_key, legal_pours, solve, CupKey, Pour};
+use crate::win_condition::{UniqueMonochrome, WinCondition};
+
+// A game gives up after this many pours, players that wander around
+// without getting anywhere would otherwise never stop
+const MAX_MOVES: usize = 500;
+
+// Plays boards without a person at the keys, by the standard rules and
+// towards the standard goal. Each player sees one board at a time and
+// can keep whatever it likes between pours, a fresh player is made for
+// every game.
+pub trait Player: Debug {
+    // The next pour, None when the player can't find one
+    fn choose_move(&mut self, board: &[Cup]) -> Option<Pour>;
+}
+
+pub fn player_from_name(name: &str, seed: u64) -> Option<Box<dyn Player>> {
+    match name {
+        "random" => Some(Box::new(RandomPlayer::new(seed))),
+        "greedy" => Some(Box::new(GreedyPlayer::default())),
+        "optimal" => Some(Box::new(OptimalPlayer::default())),
+        _ => None,
+    }
+}
+
+// Any legal pour, picked at random
+#[derive(Debug)]
+pub struct RandomPlayer {
+    rng: StdRng,
+}
+
+impl RandomPlayer {
+    pub fn new(seed: u64) -> RandomPlayer {
+        RandomPlayer {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Player for RandomPlayer {
+    fn choose_move(&mut self, board: &[Cup]) -> Option<Pour> {
+        let pours = legal_pours(board, &Standard);
+        if pours.is_empty() {
+            return None;
+        }
+        Some(pours[self.rng.random_range(0..pours.len())])
+    }
+}
+
+fn after(board: &[Cup], (src, dst): Pour) -> Option<Vec<Cup>> {
+    let (new_src, new_dst) = Standard.pour(&board[src], &board[dst]).ok()?;
+    let mut next = board.to_vec();
+    next[src] = new_src;
+    next[dst] = new_dst;
+    settle(&mut next);
+    Some(next)
+}
+
+// Takes the pour that looks closest to sorted right away, never going
+// back to a board it has already been on
+#[derive(Debug, Default)]
+pub struct GreedyPlayer {
+    seen: HashSet<Vec<CupKey>>,
+}
+
+impl Player for GreedyPlayer {
+    fn choose_move(&mut self, board: &[Cup]) -> Option<Pour> {
+        self.seen.insert(board_key(board, &UniqueMonochrome));
+        let (pour, next) = legal_pours(board, &Standard)
+            .into_iter()
+            .filter_map(|pour| Some((pour, after(board, pour)?)))
+            .filter(|(_, next)| !self.seen.contains(&board_key(next, &UniqueMonochrome)))
+            .min_by_key(|(_, next)| UniqueMonochrome.estimate(next))?;
+        self.seen.insert(board_key(&next, &UniqueMonochrome));
+        Some(pour)
+    }
+}
+
+// Follows the solver's shortest solution, solving again if the board
+// is not the one it expected
+#[derive(Debug, Default)]
+pub struct OptimalPlayer {
+    plan: Vec<Pour>,
+    expected: Option<Vec<Cup>>,
+}
+
+impl Player for OptimalPlayer {
+    fn choose_move(&mut self, board: &[Cup]) -> Option<Pour> {
+        if self.plan.is_empty() || self.expected.as_deref() != Some(board) {
+            self.plan = solve(board, &UniqueMonochrome, &Standard)?;
+            self.plan.reverse();
+        }
+        let pour = self.plan.pop()?;
+        self.expected = after(board, pour);
+        Some(pour)
+    }
+}
+
+// Plays one board until it is sorted or the player gives up, and
+// returns the pours it took to sort it
+pub fn play_game(player: &mut dyn Player, mut cups: Vec<Cup>) -> Option<usize> {
+    for moves in 0..MAX_MOVES {
+        if UniqueMonochrome.is_won(&cups) {
+            return Some(moves);
+        }
+        let (src, dst) = player.choose_move(&cups)?;
+        // Pours that break the rules end the game like any other mistake
+        if src >= cups.len() || dst >= cups.len() {
+            return None;
+        }
+        cups = after(&cups, (src, dst))?;
+    }
+    UniqueMonochrome.is_won(&cups).then_some(MAX_MOVES)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub games: usize,
+    pub wins: usize,
+    // Pours taken over all the games that were won
+    pub moves: usize,
+}
+
+impl Summary {
+    pub fn win_rate(&self) -> f64 {
+        self.wins as f64 / self.games.max(1) as f64
+    }
+
+    pub fn average_moves(&self) -> Option<f64> {
+        (self.wins > 0).then(|| self.moves as f64 / self.wins as f64)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let moves = match self.average_moves() {
+            Some(moves) => format!("{:.1} pours on average", moves),
+            None => "no wins".to_string(),
+        };
+        write!(
+            f,
+            "{:.0}% of {} games won, {}",
+            self.win_rate() * 100.0,
+            self.games,
+            moves
+        )
+    }
+}
+
+// Plays the same boards the game deals for each seed from `seed` on,
+// with a new player for every game
+pub fn simulate(
+    player: &str,
+    difficulty: SimpleDifficulties,
+    games: usize,
+    seed: u64,
+) -> Result<Summary, String> {
+    let mut summary = Summary::default();
+    for game in 0..games as u64 {
+        let seed = seed.wrapping_add(game);
+        let mut player = player_from_name(player, seed)
+            .ok_or("--simulate needs one of random, greedy or optimal")?;
+        let cups = scramble_cups(generate_cups(difficulty), seed);
+        summary.games += 1;
+        if let Some(moves) = play_game(player.as_mut(), cups) {
+            summary.wins += 1;
+            summary.moves += moves;
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test_player {
+    use super::*;
+
+    #[test]
+    fn players_sort_easy_boards() {
+        let cups = scramble_cups(generate_cups(SimpleDifficulties::Easy), 5);
+        let par = solve(&cups, &UniqueMonochrome, &Standard).unwrap().len();
+        assert_eq!(
+            play_game(&mut OptimalPlayer::default(), cups.clone()),
+            Some(par)
+        );
+        assert!(play_game(&mut GreedyPlayer::default(), cups.clone()).is_some_and(|m| m >= par));
+
+        let summary = simulate("random", SimpleDifficulties::Easy, 5, 1).unwrap();
+        assert_eq!(summary.games, 5);
+        assert!(summary.average_moves().is_none_or(|m| m >= 1.0));
+        assert!(simulate("psychic", SimpleDifficulties::Easy, 1, 1).is_err());
+    }
+
+    #[test]
+    fn greedy_never_goes_back() {
+        let mut player = GreedyPlayer::default();
+        let mut cups = scramble_cups(generate_cups(SimpleDifficulties::Medium), 2);
+        let mut seen = vec![board_key(&cups, &UniqueMonochrome)];
+        // It either sorts the board or runs out of new boards to try
+        while !UniqueMonochrome.is_won(&cups)
+            && let Some(pour) = player.choose_move(&cups)
+        {
+            cups = after(&cups, pour).unwrap();
+            assert!(!seen.contains(&board_key(&cups, &UniqueMonochrome)));
+            seen.push(board_key(&cups, &UniqueMonochrome));
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt::Debug;
+
+use crate::cup::{pour_a_into_b, unrestricted_pop_a_into_b, Cup};
+use crate::liquids::LiquidColors;
+
+// Decides which pours are allowed and how much liquid each one moves.
+// The solver plays by the same rule, so par and hints stay honest.
+pub trait PourRule: Debug {
+    // The name --rules knows it by
+    fn name(&self) -> &'static str;
+
+    // Shown to the player when the rule is not the standard one
+    fn describe(&self) -> String;
+
+    fn pour(&self, a: &Cup, b: &Cup) -> Result<(Cup, Cup), String>;
+
+    // Rules that let nearly any cup pour into any other leave far too
+    // many boards to search, so the solver doesn't try
+    fn is_wide_open(&self) -> bool {
+        false
+    }
+
+    // Goals estimate in whole runs, which says little about how many
+    // pours are left when every pour moves a single unit
+    fn pours_single_units(&self) -> bool {
+        false
+    }
+}
+
+impl Default for Box<dyn PourRule> {
+    fn default() -> Self {
+        Box::new(Standard)
+    }
+}
+
+// The whole top run moves onto a matching color or an empty cup
+#[derive(Debug)]
+pub struct Standard;
+
+impl PourRule for Standard {
+    fn name(&self) -> &'static str {
+        "standard"
+    }
+
+    fn describe(&self) -> String {
+        "Pour the top color onto the same color or into an empty cup".to_string()
+    }
+
+    fn pour(&self, a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
+        pour_a_into_b(a, b)
+    }
+}
+
+// Colors still have to match, but only one unit moves per pour
+#[derive(Debug)]
+pub struct SingleUnit;
+
+impl PourRule for SingleUnit {
+    fn name(&self) -> &'static str {
+        "single"
+    }
+
+    fn describe(&self) -> String {
+        "Every pour moves a single unit".to_string()
+    }
+
+    fn pour(&self, a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
+        if let (Some(a_top), Some(b_top)) = (a.liquids.last(), b.liquids.last())
+            && !a_top.matches(b_top)
+            && !a_top.is_special()
+            && !b_top.is_special()
+        {
+            return Err("Source and destination colors do not match".to_string());
+        }
+        unrestricted_pop_a_into_b(a, b)
+    }
+
+    fn pours_single_units(&self) -> bool {
+        true
+    }
+}
+
+// A new color can only be started in an empty cup, one unit at a time,
+// while runs still pour whole onto their own color
+#[derive(Debug)]
+pub struct AnyOntoEmpty;
+
+impl PourRule for AnyOntoEmpty {
+    fn name(&self) -> &'static str {
+        "empty"
+    }
+
+    fn describe(&self) -> String {
+        "An empty cup takes one unit of any color at a time".to_string()
+    }
+
+    fn pour(&self, a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
+        match b.liquids.is_empty() && a.liquids.last() != Some(&LiquidColors::Shot) {
+            true => unrestricted_pop_a_into_b(a, b),
+            false => pour_a_into_b(a, b),
+        }
+    }
+}
+
+// Anything goes, one unit at a time
+#[derive(Debug)]
+pub struct Sandbox;
+
+impl PourRule for Sandbox {
+    fn name(&self) -> &'static str {
+        "sandbox"
+    }
+
+    fn describe(&self) -> String {
+        "Sandbox, any color can be poured onto any other".to_string()
+    }
+
+    fn pour(&self, a: &Cup, b: &Cup) -> Result<(Cup, Cup), String> {
+        unrestricted_pop_a_into_b(a, b)
+    }
+
+    fn is_wide_open(&self) -> bool {
+        true
+    }
+}
+
+pub fn rule_from_name(name: &str) -> Option<Box<dyn PourRule>> {
+    match name {
+        "standard" => Some(Box::new(Standard)),
+        "single" => Some(Box::new(SingleUnit)),
+        "empty" => Some(Box::new(AnyOntoEmpty)),
+        "sandbox" => Some(Box::new(Sandbox)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_pour_rule {
+    use super::*;
+    use crate::cup::cup;
+    use crate::liquids::LiquidColors::{Green, Red, Shot, Syrup};
+
+    #[test]
+    fn single_unit_moves_one() {
+        let (a, b) = SingleUnit
+            .pour(&cup(4, vec![Green, Red, Red]), &cup(4, vec![Red]))
+            .unwrap();
+        assert_eq!(a.liquids, vec![Green, Red]);
+        assert_eq!(b.liquids, vec![Red, Red]);
+        assert!(SingleUnit.pour(&cup(4, vec![Red]), &cup(4, vec![Green])).is_err());
+    }
+
+    #[test]
+    fn empty_cups_take_one_unit() {
+        let (a, b) = AnyOntoEmpty.pour(&cup(4, vec![Green, Red, Red]), &cup(4, vec![])).unwrap();
+        assert_eq!(a.liquids, vec![Green, Red]);
+        assert_eq!(b.liquids, vec![Red]);
+        // Onto a color the whole run still moves, if it matches
+        let (a, b) = AnyOntoEmpty.pour(&cup(4, vec![Green, Red, Red]), &b).unwrap();
+        assert_eq!(a.liquids, vec![Green]);
+        assert_eq!(b.liquids, vec![Red, Red, Red]);
+        let err = AnyOntoEmpty.pour(&a, &b).unwrap_err();
+        assert_eq!(err, "Source and destination colors do not match");
+        assert!(AnyOntoEmpty.pour(&cup(4, vec![Shot]), &cup(4, vec![])).is_err());
+    }
+
+    #[test]
+    fn syrup_goes_with_any_color() {
+        let rules: [&dyn PourRule; 4] = [&Standard, &SingleUnit, &AnyOntoEmpty, &Sandbox];
+        for rule in rules {
+            assert!(rule.pour(&cup(4, vec![Syrup]), &cup(4, vec![Green])).is_ok());
+            assert!(rule.pour(&cup(4, vec![Red]), &cup(4, vec![Syrup])).is_ok());
+        }
+    }
+
+    #[test]
+    fn sandbox_ignores_colors() {
+        let (_, b) = Sandbox.pour(&cup(4, vec![Green]), &cup(4, vec![Red])).unwrap();
+        assert_eq!(b.liquids, vec![Red, Green]);
+    }
+}
@@ -0,0 +1,379 @@
+use ratatui::style::Color;
+
+use crate::cup::{Cup, CupKind, Lock};
+use crate::liquids::LiquidColors;
+
+// Boards drawn outside the terminal, for bug reports and docs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderFormat {
+    Svg,
+    // A page with the SVG on it
+    Html,
+    // Colored text for pasting into a terminal
+    Ansi,
+}
+
+pub fn format_from_name(name: &str) -> Option<RenderFormat> {
+    match name {
+        "svg" => Some(RenderFormat::Svg),
+        "html" => Some(RenderFormat::Html),
+        "ansi" => Some(RenderFormat::Ansi),
+        _ => None,
+    }
+}
+
+pub fn render_board(cups: &[Cup], format: RenderFormat) -> String {
+    match format {
+        RenderFormat::Svg => render_svg(cups),
+        RenderFormat::Html => render_html(cups),
+        RenderFormat::Ansi => render_ansi(cups),
+    }
+}
+
+pub fn color_code(color: Color, background: bool) -> String {
+    let base = if background { 40 } else { 30 };
+    match color {
+        Color::Reset => (base + 9).to_string(),
+        Color::Black => base.to_string(),
+        Color::Red => (base + 1).to_string(),
+        Color::Green => (base + 2).to_string(),
+        Color::Yellow => (base + 3).to_string(),
+        Color::Blue => (base + 4).to_string(),
+        Color::Magenta => (base + 5).to_string(),
+        Color::Cyan => (base + 6).to_string(),
+        Color::Gray => (base + 7).to_string(),
+        Color::DarkGray => (base + 60).to_string(),
+        Color::LightRed => (base + 61).to_string(),
+        Color::LightGreen => (base + 62).to_string(),
+        Color::LightYellow => (base + 63).to_string(),
+        Color::LightBlue => (base + 64).to_string(),
+        Color::LightMagenta => (base + 65).to_string(),
+        Color::LightCyan => (base + 66).to_string(),
+        Color::White => (base + 67).to_string(),
+        Color::Indexed(i) => format!("{};5;{}", base + 8, i),
+        Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+    }
+}
+
+// xterm's default colors for the first sixteen indexes
+const BASE_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+fn rgb(color: Color) -> (u8, u8, u8) {
+    let index = match color {
+        Color::Rgb(r, g, b) => return (r, g, b),
+        Color::Indexed(i) => i,
+        Color::Reset | Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+    };
+    match index {
+        0..=15 => BASE_COLORS[index as usize],
+        // A 6x6x6 color cube, then a ramp of greys
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + 40 * v };
+            let i = index - 16;
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        _ => {
+            let grey = 8 + 10 * (index - 232);
+            (grey, grey, grey)
+        }
+    }
+}
+
+fn hex(color: Color) -> String {
+    let (r, g, b) = rgb(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+// The same labels the game puts across special liquids
+fn liquid_label(liquid: &LiquidColors) -> Option<String> {
+    match liquid {
+        LiquidColors::Syrup => Some("≈ ≈ ≈".to_string()),
+        LiquidColors::Ice(_, moves) => Some(format!("ice {}", moves)),
+        LiquidColors::Shot => Some("shot".to_string()),
+        _ => None,
+    }
+}
+
+fn kind_label(kind: &CupKind) -> Option<String> {
+    match kind {
+        CupKind::Normal => None,
+        CupKind::OneWay => Some("in only".to_string()),
+        CupKind::Locked(Lock::Pours(pours)) => Some(format!("🔒 {} pours", pours)),
+        CupKind::Locked(Lock::Color(color)) => {
+            Some(format!("🔒 {}", color.to_string().to_lowercase()))
+        }
+    }
+}
+
+// Sizes in the SVG, in pixels
+const UNIT_HEIGHT: usize = 24;
+const CUP_WIDTH: usize = 64;
+const CUP_GAP: usize = 16;
+const MARGIN: usize = 16;
+const LABEL_HEIGHT: usize = 20;
+const BACKGROUND: &str = "#1e1e1e";
+const TEXT: &str = "#e5e5e5";
+
+// Cups stand side by side on the same shelf like in the game, with
+// hidden units drawn as they look to the player
+pub fn render_svg(cups: &[Cup]) -> String {
+    let tallest = cups.iter().map(|c| c.capacity).max().unwrap_or(0);
+    let width = 2 * MARGIN + cups.len() * CUP_WIDTH + cups.len().saturating_sub(1) * CUP_GAP;
+    let height = 2 * MARGIN + 2 * LABEL_HEIGHT + tallest * UNIT_HEIGHT;
+    let bottom = MARGIN + LABEL_HEIGHT + tallest * UNIT_HEIGHT;
+    let mut svg = vec![
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"monospace\" font-size=\"12\" text-anchor=\"middle\">",
+            width, height, width, height
+        ),
+        format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+            width, height, BACKGROUND
+        ),
+    ];
+    for (i, cup) in cups.iter().enumerate() {
+        let x = MARGIN + i * (CUP_WIDTH + CUP_GAP);
+        let middle = x + CUP_WIDTH / 2;
+        let top = bottom - cup.capacity * UNIT_HEIGHT;
+        for (k, liquid) in cup.liquids.iter().enumerate() {
+            let y = bottom - (k + 1) * UNIT_HEIGHT;
+            let (fill, label, title) = match cup.is_revealed(k) {
+                true => (liquid.to_color(), liquid_label(liquid), liquid.to_string()),
+                false => (
+                    Color::DarkGray,
+                    Some("? ? ?".to_string()),
+                    "Hidden".to_string(),
+                ),
+            };
+            svg.push(format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"><title>{}</title></rect>",
+                x + 2,
+                y,
+                CUP_WIDTH - 4,
+                UNIT_HEIGHT,
+                hex(fill),
+                title
+            ));
+            if let Some(label) = label {
+                let color = match liquid {
+                    LiquidColors::Ice(..) if cup.is_revealed(k) => Color::Black,
+                    _ => Color::White,
+                };
+                svg.push(format!(
+                    "<text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>",
+                    middle,
+                    y + UNIT_HEIGHT / 2 + 4,
+                    hex(color),
+                    label
+                ));
+            }
+        }
+        // Open at the top, dashed when nothing can be poured back out
+        let dashes = match cup.kind {
+            CupKind::OneWay => " stroke-dasharray=\"4 3\"",
+            _ => "",
+        };
+        svg.push(format!(
+            "<path d=\"M {} {} V {} H {} V {}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"{}/>",
+            x,
+            top,
+            bottom,
+            x + CUP_WIDTH,
+            top,
+            TEXT,
+            dashes
+        ));
+        if let CupKind::Locked(_) = cup.kind {
+            svg.push(format!(
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"4\"/>",
+                x - 2,
+                top,
+                x + CUP_WIDTH + 2,
+                top,
+                TEXT
+            ));
+        }
+        if let Some(label) = kind_label(&cup.kind) {
+            svg.push(format!(
+                "<text x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>",
+                middle,
+                top - 6,
+                TEXT,
+                label
+            ));
+        }
+        svg.push(format!(
+            "<text x=\"{}\" y=\"{}\" fill=\"{}\">[{}]</text>",
+            middle,
+            bottom + LABEL_HEIGHT - 4,
+            TEXT,
+            i + 1
+        ));
+    }
+    svg.push("</svg>".to_string());
+    svg.join("\n") + "\n"
+}
+
+pub fn render_html(cups: &[Cup]) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>terminal barista board</title>\n</head>\n<body style=\"background: {}\">\n{}</body>\n</html>\n",
+        BACKGROUND,
+        render_svg(cups)
+    )
+}
+
+fn paint(text: &str, fg: Color, bg: Color) -> String {
+    format!(
+        "\x1b[{};{}m{}\x1b[0m",
+        color_code(fg, false),
+        color_code(bg, true),
+        text
+    )
+}
+
+// Each unit is two characters wide, a cup is four with its walls
+fn ansi_unit(cup: &Cup, k: usize) -> String {
+    let Some(liquid) = cup.liquids.get(k) else {
+        return "  ".to_string();
+    };
+    if !cup.is_revealed(k) {
+        return paint("??", Color::Gray, Color::DarkGray);
+    }
+    match liquid {
+        LiquidColors::Syrup => paint("≈≈", Color::White, liquid.to_color()),
+        LiquidColors::Ice(_, moves) => paint(
+            &format!("{:>2}", (*moves).min(99)),
+            Color::Black,
+            liquid.to_color(),
+        ),
+        LiquidColors::Shot => paint("xx", Color::LightRed, liquid.to_color()),
+        _ => paint("  ", Color::Reset, liquid.to_color()),
+    }
+}
+
+pub fn render_ansi(cups: &[Cup]) -> String {
+    let tallest = cups.iter().map(|c| c.capacity).max().unwrap_or(0);
+    let mut lines = vec![];
+    // One row above the tallest cup for lids
+    for row in (0..=tallest).rev() {
+        let line: Vec<String> = cups
+            .iter()
+            .map(|cup| {
+                let wall = match cup.kind {
+                    CupKind::OneWay => "┆",
+                    _ => "│",
+                };
+                match (row, &cup.kind) {
+                    (row, _) if row < cup.capacity => {
+                        format!("{}{}{}", wall, ansi_unit(cup, row), wall)
+                    }
+                    (row, CupKind::Locked(_)) if row == cup.capacity => "┌──┐".to_string(),
+                    _ => "    ".to_string(),
+                }
+            })
+            .collect();
+        lines.push(line.join(" ").trim_end().to_string());
+    }
+    lines.push(vec!["╰──╯"; cups.len()].join(" "));
+    let numbers: Vec<String> = (1..=cups.len()).map(|i| format!("{:^4}", i)).collect();
+    lines.push(numbers.join(" ").trim_end().to_string());
+    // Boards without a locked cup don't need the lid row
+    if lines[0].is_empty() {
+        lines.remove(0);
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod test_render {
+    use super::*;
+    use crate::cup::cup;
+
+    fn board() -> Vec<Cup> {
+        vec![
+            Cup {
+                hidden: 1,
+                ..cup(3, vec![LiquidColors::Red, LiquidColors::Brown, LiquidColors::Red])
+            },
+            Cup {
+                kind: CupKind::OneWay,
+                ..cup(2, vec![LiquidColors::Ice(Box::new(LiquidColors::Brown), 2)])
+            },
+            Cup {
+                kind: CupKind::Locked(Lock::Pours(4)),
+                ..cup(3, vec![])
+            },
+        ]
+    }
+
+    #[test]
+    fn palette_matches_the_terminal() {
+        assert_eq!(hex(LiquidColors::Red.to_color()), "#ff0000");
+        // Brown is from the 256 color cube
+        assert_eq!(hex(LiquidColors::Brown.to_color()), "#875f00");
+        assert_eq!(rgb(Color::Indexed(244)), (128, 128, 128));
+    }
+
+    #[test]
+    fn renders_every_format() {
+        let svg = render_board(&board(), RenderFormat::Svg);
+        assert!(
+            svg.starts_with(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"256\" height=\"144\""
+            )
+        );
+        assert!(svg.trim_end().ends_with("</svg>"));
+        // The hidden red at the bottom doesn't give itself away
+        assert_eq!(svg.matches("#ff0000").count(), 1);
+        assert_eq!(svg.matches("<title>Hidden</title>").count(), 1);
+        assert!(svg.contains(">ice 2</text>"));
+        assert!(svg.contains("stroke-dasharray"));
+        assert!(svg.contains(">🔒 4 pours</text>"));
+        assert!(svg.contains(">[3]</text>"));
+
+        let html = render_board(&board(), RenderFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains(&svg));
+
+        let ansi = render_board(&board(), RenderFormat::Ansi);
+        let lines: Vec<&str> = ansi.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "          ┌──┐");
+        assert!(lines[1].starts_with("│\x1b[39;101m  \x1b[0m│"));
+        assert_eq!(lines[4], "╰──╯ ╰──╯ ╰──╯");
+        assert_eq!(lines[5], " 1    2    3");
+        assert!(!render_ansi(&board()[..2]).starts_with(' '));
+    }
+}
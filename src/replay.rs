@@ -0,0 +1,258 @@
+use std::{
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::cup::{Cup, settle};
+use crate::level::{Level, parse_share_code, share_code};
+use crate::pour_rule::PourRule;
+use crate::solver::Pour;
+use crate::speed::Speed;
+use crate::storage::save_to_data_dir;
+
+// A replay file has the board's seed, how the game ended and the
+// starting board as a share code, followed by every pour with the
+// cups numbered from 1 and the seconds into the game it was made:
+//
+//   seed 48213
+//   outcome won
+//   board 4.red.green/4.green.red/4
+//   pour 1 3 1.20
+//   pour 2 1 2.85
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub won: bool,
+    // The starting board, with its goal and rules
+    pub level: Level,
+    pub pours: Vec<(Duration, Pour)>,
+}
+
+// Long stops to think are cut short when watching
+const MAX_PAUSE: Duration = Duration::from_secs(3);
+
+pub fn replay_to_string(replay: &Replay) -> String {
+    let mut lines = vec![
+        format!("seed {}", replay.seed),
+        format!("outcome {}", if replay.won { "won" } else { "lost" }),
+        format!("board {}", share_code(&replay.level)),
+    ];
+    for (at, (src, dst)) in &replay.pours {
+        lines.push(format!(
+            "pour {} {} {:.2}",
+            src + 1,
+            dst + 1,
+            at.as_secs_f64()
+        ));
+    }
+    lines.join("\n") + "\n"
+}
+
+fn parse_pour(src: &str, dst: &str, at: &str) -> Option<(Duration, Pour)> {
+    let src = src.parse::<usize>().ok()?.checked_sub(1)?;
+    let dst = dst.parse::<usize>().ok()?.checked_sub(1)?;
+    let at = Duration::try_from_secs_f64(at.parse().ok()?).ok()?;
+    Some((at, (src, dst)))
+}
+
+pub fn parse_replay(text: &str) -> Result<Replay, String> {
+    let mut replay = Replay::default();
+    let mut board = None;
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["seed", seed] => {
+                replay.seed = seed
+                    .parse()
+                    .map_err(|_| format!("Line {}: the seed is not a number", line_no + 1))?
+            }
+            ["outcome", "won"] => replay.won = true,
+            ["outcome", "lost"] => replay.won = false,
+            ["board", code] => board = Some(parse_share_code(code)?),
+            ["pour", src, dst, at] => replay.pours.push(parse_pour(src, dst, at).ok_or(
+                format!("Line {}: a pour needs two cups and a time", line_no + 1),
+            )?),
+            _ => return Err(format!("Line {}: unknown line '{}'", line_no + 1, line)),
+        }
+    }
+    replay.level = board.ok_or("A replay needs a board line")?;
+    Ok(replay)
+}
+
+pub fn load_replay(path: &str) -> Result<Replay, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    parse_replay(&text)
+}
+
+// Replays go in a replays folder next to the stats
+pub fn save_replay(replay: &Replay) -> Result<PathBuf, String> {
+    save_to_data_dir("replays", "replay", &replay_to_string(replay))
+}
+
+// A replay being watched. Every board along the way is worked out up
+// front, which also checks each pour is still legal.
+#[derive(Debug, Clone)]
+pub struct Playback {
+    pub replay: Replay,
+    // The starting board and the board after each pour
+    pub boards: Vec<Vec<Cup>>,
+    // Index into boards
+    pub position: usize,
+    pub paused: bool,
+    pub speed: Speed,
+    pub last_step: Instant,
+}
+
+impl Playback {
+    pub fn new(replay: Replay, rule: &dyn PourRule) -> Result<Playback, String> {
+        let mut boards = vec![replay.level.cups.clone()];
+        for (i, (_, (src, dst))) in replay.pours.iter().enumerate() {
+            let board = &boards[i];
+            if *src >= board.len() || *dst >= board.len() {
+                return Err(format!(
+                    "Pour {} is into a cup the board doesn't have",
+                    i + 1
+                ));
+            }
+            let (new_src, new_dst) = rule.pour(&board[*src], &board[*dst]).map_err(|e| {
+                format!(
+                    "Pour {} ({} into {}) is not allowed: {}",
+                    i + 1,
+                    src + 1,
+                    dst + 1,
+                    e
+                )
+            })?;
+            let mut next = board.clone();
+            next[*src] = new_src;
+            next[*dst] = new_dst;
+            settle(&mut next);
+            boards.push(next);
+        }
+        Ok(Playback {
+            replay,
+            boards,
+            position: 0,
+            paused: false,
+            speed: Speed::default(),
+            last_step: Instant::now(),
+        })
+    }
+
+    pub fn board(&self) -> &[Cup] {
+        &self.boards[self.position]
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.position == self.boards.len() - 1
+    }
+
+    // How long until the next pour is shown, as long as the player took
+    // between the two pours. None while paused or once every pour has
+    // been shown.
+    pub fn until_next_step(&self) -> Option<Duration> {
+        if self.paused {
+            return None;
+        }
+        let (next, _) = self.replay.pours.get(self.position)?;
+        let previous = match self.position {
+            0 => Duration::ZERO,
+            position => self.replay.pours[position - 1].0,
+        };
+        let pause = self
+            .speed
+            .scale(next.saturating_sub(previous).min(MAX_PAUSE));
+        Some(pause.saturating_sub(self.last_step.elapsed()))
+    }
+
+    pub fn seek(&mut self, position: usize) {
+        self.position = position.min(self.boards.len() - 1);
+        self.last_step = Instant::now();
+    }
+
+    pub fn status(&self) -> String {
+        let state = match (self.is_over(), self.replay.won) {
+            (true, true) => "won",
+            (true, false) => "lost",
+            (false, _) if self.paused => "paused",
+            (false, _) => "playing",
+        };
+        format!(
+            "Pour {}/{}, {} at {}",
+            self.position,
+            self.boards.len() - 1,
+            state,
+            self.speed.label()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_replay {
+    use super::*;
+    use crate::level::parse_level;
+    use crate::pour_rule::Standard;
+
+    fn replay(pours: Vec<Pour>) -> Replay {
+        Replay {
+            seed: 7,
+            won: true,
+            level: parse_level("4 red green\n4 green red\n4").unwrap(),
+            pours: pours
+                .into_iter()
+                .enumerate()
+                .map(|(i, pour)| (Duration::from_millis(1500 * i as u64 + 250), pour))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn replays_round_trip() {
+        let text = replay_to_string(&replay(vec![(0, 2), (1, 0)]));
+        assert_eq!(
+            text,
+            "seed 7\noutcome won\nboard 4.red.green/4.green.red/4\npour 1 3 0.25\npour 2 1 1.75\n"
+        );
+        let parsed = parse_replay(&text).unwrap();
+        assert_eq!(replay_to_string(&parsed), text);
+        assert!(parse_replay("seed 7\npour 1 2 0.5").is_err());
+        assert!(parse_replay("board 4\npour 0 2 0.5").is_err());
+    }
+
+    #[test]
+    fn mystery_replays_keep_hidden_layers() {
+        // Hidden reds stay behind when the red on top is poured
+        let replay = Replay {
+            level: parse_level("hidden:2 4 red red red\n4 green\n4").unwrap(),
+            pours: vec![(Duration::from_secs(1), (0, 2))],
+            ..Default::default()
+        };
+        let parsed = parse_replay(&replay_to_string(&replay)).unwrap();
+        let mut playback = Playback::new(parsed, &Standard).unwrap();
+        playback.seek(1);
+        assert_eq!(playback.board()[2].liquids.len(), 1);
+        assert_eq!(playback.board()[0].hidden, 1);
+    }
+
+    #[test]
+    fn playback_checks_every_pour() {
+        let mut playback = Playback::new(replay(vec![(0, 2), (1, 0), (1, 2)]), &Standard).unwrap();
+        assert_eq!(playback.boards.len(), 4);
+        playback.seek(10);
+        assert!(playback.is_over());
+        assert_eq!(playback.until_next_step(), None);
+        assert!(crate::cup::are_cups_solved(playback.board()));
+
+        let err = Playback::new(replay(vec![(0, 1)]), &Standard).unwrap_err();
+        assert!(
+            err.starts_with("Pour 1 (1 into 2) is not allowed"),
+            "{}",
+            err
+        );
+    }
+}
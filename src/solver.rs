@@ -0,0 +1,539 @@
+use crate::cup::{settle, Cup, CupKind, Lock};
+use crate::liquids::LiquidColors;
+use crate::pour_rule::PourRule;
+use crate::win_condition::WinCondition;
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::Entry, BinaryHeap, HashMap},
+};
+
+// Upper bound on the number of boards the search will expand before
+// giving up. Keeps hints and par calculation responsive on big boards.
+const SEARCH_LIMIT: usize = 200_000;
+// The limit when the goal's estimate can't steer the search well, which
+// would otherwise use up the whole limit on most boards
+const QUICK_SEARCH_LIMIT: usize = 50_000;
+// Rules that allow many pours per board fill memory long before the
+// expansion limit, so the boards kept around are capped too
+const BOARD_LIMIT: usize = 300_000;
+// Boards looked at when the solver finds no way through, before
+// deciding there are too many to tell whether it is a dead end
+const DEAD_END_LIMIT: usize = 20_000;
+
+pub type Pour = (usize, usize);
+
+// A board reached during the search, and the board + pour it came from
+type Node = (Vec<Cup>, Option<(usize, Pour)>);
+// Everything that makes two cups play differently
+pub type CupKey = (usize, CupKind, Vec<LiquidColors>);
+
+pub fn legal_pours(cups: &[Cup], rule: &dyn PourRule) -> Vec<Pour> {
+    let mut pours = vec![];
+    for src in 0..cups.len() {
+        for dst in 0..cups.len() {
+            if src != dst && rule.pour(&cups[src], &cups[dst]).is_ok() {
+                pours.push((src, dst));
+            }
+        }
+    }
+    pours
+}
+
+// Cup order does not matter when comparing boards, so equivalent
+// boards with their cups shuffled around share a single key. Cups the
+// goal is tied to keep their place, ahead of the shuffled ones.
+pub fn board_key(cups: &[Cup], goal: &dyn WinCondition) -> Vec<CupKey> {
+    let (mut key, mut shuffled): (Vec<CupKey>, Vec<CupKey>) = (vec![], vec![]);
+    for (idx, c) in cups.iter().enumerate() {
+        let cup_key = (c.capacity, c.kind.clone(), c.liquids.clone());
+        match goal.keeps_place(idx) {
+            true => key.push(cup_key),
+            false => shuffled.push(cup_key),
+        }
+    }
+    shuffled.sort();
+    key.extend(shuffled);
+    key
+}
+
+fn apply(cups: &[Cup], (src, dst): Pour, rule: &dyn PourRule) -> Option<Vec<Cup>> {
+    let (new_src, new_dst) = rule.pour(&cups[src], &cups[dst]).ok()?;
+    let mut next = cups.to_vec();
+    next[src] = new_src;
+    next[dst] = new_dst;
+    settle(&mut next);
+    Some(next)
+}
+
+// Every legal pour along with the board it leads to
+fn next_boards(cups: &[Cup], rule: &dyn PourRule) -> Vec<(Pour, Vec<Cup>)> {
+    let mut boards = vec![];
+    for src in 0..cups.len() {
+        for dst in 0..cups.len() {
+            // Most pairs can't pour at all, so skip them before the
+            // rule builds an error for them
+            if src != dst
+                && !cups[src].liquids.is_empty()
+                && let Some(next) = apply(cups, (src, dst), rule)
+            {
+                boards.push(((src, dst), next));
+            }
+        }
+    }
+    boards
+}
+
+// Moving a whole cup into an empty one just like it never helps,
+// unless the goal cares which cup it ends up in or the pour itself
+// is what counts down a lock or thaws ice
+fn is_pointless(cups: &[Cup], next: &[Cup], (src, dst): Pour, goal: &dyn WinCondition) -> bool {
+    !counts_pours(cups)
+        && !goal.keeps_place(src)
+        && !goal.keeps_place(dst)
+        && cups[dst].liquids.is_empty()
+        && next[src].liquids.is_empty()
+        && cups[src].capacity == cups[dst].capacity
+        && cups[src].kind == cups[dst].kind
+}
+
+fn counts_pours(cups: &[Cup]) -> bool {
+    cups.iter().any(|cup| {
+        matches!(cup.kind, CupKind::Locked(Lock::Pours(_)))
+            || cup.liquids.iter().any(|l| matches!(l, LiquidColors::Ice(..)))
+    })
+}
+
+// Finds the shortest sequence of pours that reaches the goal, or `None`
+// if the board cannot be solved (or is too large to search, which
+// boards under wide open rules always are).
+pub fn solve(cups: &[Cup], goal: &dyn WinCondition, rule: &dyn PourRule) -> Option<Vec<Pour>> {
+    if rule.is_wide_open() {
+        return None;
+    }
+    solve_within(cups, goal, rule, search_limit(goal, rule))
+}
+
+fn search_limit(goal: &dyn WinCondition, rule: &dyn PourRule) -> usize {
+    match goal.estimate_is_rough() || rule.pours_single_units() {
+        true => QUICK_SEARCH_LIMIT,
+        false => SEARCH_LIMIT,
+    }
+}
+
+// Like `solve`, but gives up sooner. The generator uses it to pass on
+// boards that would take too long to check.
+pub fn solve_within(
+    cups: &[Cup],
+    goal: &dyn WinCondition,
+    rule: &dyn PourRule,
+    limit: usize,
+) -> Option<Vec<Pour>> {
+    search(cups, goal, rule, limit, &|b| goal.is_won(b), &|b| goal.estimate(b))
+}
+
+fn hidden_units(cups: &[Cup]) -> usize {
+    cups.iter().map(|c| c.hidden).sum()
+}
+
+// Finds the shortest sequence of pours that wins or uncovers a hidden
+// layer. Hidden colors don't change which pours are legal, so the
+// search never relies on anything the player can't see.
+pub fn next_reveal(cups: &[Cup], goal: &dyn WinCondition, rule: &dyn PourRule) -> Option<Vec<Pour>> {
+    let hidden = hidden_units(cups);
+    // The goal's estimate knows nothing about reveals, so search blind
+    let is_done = |b: &[Cup]| goal.is_won(b) || hidden_units(b) < hidden;
+    if rule.is_wide_open() {
+        return None;
+    }
+    search(cups, goal, rule, search_limit(goal, rule), &is_done, &|_| 0)
+}
+
+fn search(
+    cups: &[Cup],
+    goal: &dyn WinCondition,
+    rule: &dyn PourRule,
+    limit: usize,
+    is_done: &dyn Fn(&[Cup]) -> bool,
+    estimate: &dyn Fn(&[Cup]) -> usize,
+) -> Option<Vec<Pour>> {
+    let mut boards: Vec<Node> = vec![(cups.to_vec(), None)];
+    let mut best_depth: HashMap<Vec<CupKey>, usize> = HashMap::new();
+    best_depth.insert(board_key(cups, goal), 0);
+    let mut frontier = BinaryHeap::new();
+    frontier.push(Reverse((estimate(cups), 0, 0)));
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, depth, idx))) = frontier.pop() {
+        if is_done(&boards[idx].0) {
+            return Some(path_to(&boards, idx));
+        }
+        expanded += 1;
+        if expanded > limit || boards.len() > BOARD_LIMIT {
+            return None;
+        }
+        for (pour, next) in next_boards(&boards[idx].0, rule) {
+            if is_pointless(&boards[idx].0, &next, pour, goal) {
+                continue;
+            }
+            match best_depth.entry(board_key(&next, goal)) {
+                Entry::Occupied(seen) if *seen.get() <= depth + 1 => continue,
+                Entry::Occupied(mut seen) => {
+                    seen.insert(depth + 1);
+                }
+                Entry::Vacant(new) => {
+                    new.insert(depth + 1);
+                }
+            }
+            let estimate = estimate(&next);
+            boards.push((next, Some((idx, pour))));
+            let next_idx = boards.len() - 1;
+            frontier.push(Reverse((depth + 1 + estimate, depth + 1, next_idx)));
+        }
+    }
+    None
+}
+
+// Every board reachable from a starting board, numbered in the order
+// they were found with the start as 0
+#[derive(Debug, Clone)]
+pub struct StateSpace {
+    pub boards: Vec<Vec<Cup>>,
+    // The pours out of each board and the board each one leads to
+    pub pours: Vec<Vec<(Pour, usize)>>,
+    // Fewest pours from the start to each board
+    pub depths: Vec<usize>,
+    // Boards are expanded in order, those past this one were found but
+    // their pours were never looked at
+    pub expanded: usize,
+}
+
+impl StateSpace {
+    pub fn is_complete(&self) -> bool {
+        self.expanded == self.boards.len()
+    }
+
+    // Boards the goal can't be reached from any more. When only part
+    // of the space was explored this is just the boards with no pours.
+    pub fn dead_ends(&self, goal: &dyn WinCondition) -> Vec<usize> {
+        if !self.is_complete() {
+            return (0..self.expanded)
+                .filter(|i| self.pours[*i].is_empty() && !goal.is_won(&self.boards[*i]))
+                .collect();
+        }
+        let mut can_win: Vec<bool> = self.boards.iter().map(|b| goal.is_won(b)).collect();
+        // Keep spreading backwards from won boards until nothing changes
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..self.boards.len() {
+                if !can_win[i] && self.pours[i].iter().any(|(_, next)| can_win[*next]) {
+                    can_win[i] = true;
+                    changed = true;
+                }
+            }
+        }
+        (0..self.boards.len()).filter(|i| !can_win[*i]).collect()
+    }
+
+    // The boards along the shortest way from the start to a won board.
+    // Boards are found breadth first, so the first won one is nearest.
+    pub fn shortest_path(&self, goal: &dyn WinCondition) -> Option<Vec<usize>> {
+        let mut idx = (0..self.boards.len()).find(|i| goal.is_won(&self.boards[*i]))?;
+        let mut path = vec![idx];
+        while idx != 0 {
+            idx = (0..self.expanded).find(|i| {
+                self.depths[*i] + 1 == self.depths[idx]
+                    && self.pours[*i].iter().any(|(_, next)| *next == idx)
+            })?;
+            path.push(idx);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+// Explores breadth first from the starting board, stopping once
+// `limit` boards have been found.
+pub fn explore(cups: &[Cup], goal: &dyn WinCondition, rule: &dyn PourRule, limit: usize) -> StateSpace {
+    explore_to_depth(cups, goal, rule, limit, usize::MAX)
+}
+
+// Like `explore`, but boards `max_depth` pours from the start are
+// found without being expanded.
+pub fn explore_to_depth(
+    cups: &[Cup],
+    goal: &dyn WinCondition,
+    rule: &dyn PourRule,
+    limit: usize,
+    max_depth: usize,
+) -> StateSpace {
+    let mut space = StateSpace {
+        boards: vec![cups.to_vec()],
+        pours: vec![],
+        depths: vec![0],
+        expanded: 0,
+    };
+    let mut seen: HashMap<Vec<CupKey>, usize> = HashMap::new();
+    seen.insert(board_key(cups, goal), 0);
+    while space.expanded < space.boards.len()
+        && space.boards.len() < limit
+        && space.depths[space.expanded] < max_depth
+    {
+        let board = space.boards[space.expanded].clone();
+        let depth = space.depths[space.expanded];
+        let mut pours = vec![];
+        // A won board is the end of the line
+        if !goal.is_won(&board) {
+            for (pour, next) in next_boards(&board, rule) {
+                let key = board_key(&next, goal);
+                let idx = *seen.entry(key).or_insert_with(|| {
+                    space.boards.push(next);
+                    space.depths.push(depth + 1);
+                    space.boards.len() - 1
+                });
+                pours.push((pour, idx));
+            }
+        }
+        space.pours.push(pours);
+        space.expanded += 1;
+    }
+    space
+}
+
+// Whether the goal can't be reached from the board any more. `None`
+// when there are too many boards past it to be sure either way.
+pub fn is_dead_end(cups: &[Cup], goal: &dyn WinCondition, rule: &dyn PourRule) -> Option<bool> {
+    if rule.is_wide_open() {
+        return None;
+    }
+    // A solution settles it quickly on most boards, a board that really
+    // is stuck usually has few boards past it
+    if solve(cups, goal, rule).is_some() {
+        return Some(false);
+    }
+    let space = explore(cups, goal, rule, DEAD_END_LIMIT);
+    if space.boards.iter().any(|b| goal.is_won(b)) {
+        return Some(false);
+    }
+    space.is_complete().then_some(true)
+}
+
+fn path_to(boards: &[Node], mut idx: usize) -> Vec<Pour> {
+    let mut pours = vec![];
+    while let Some((parent, pour)) = boards[idx].1 {
+        pours.push(pour);
+        idx = parent;
+    }
+    pours.reverse();
+    pours
+}
+
+
+#[cfg(test)]
+mod test_solve {
+    use super::*;
+    use crate::cup::cup;
+    use crate::pour_rule::{Sandbox, SingleUnit, Standard};
+    use crate::win_condition::{FillCups, TargetLayout, UniqueMonochrome, WinCondition};
+
+    #[test]
+    fn solved_board_needs_no_pours() {
+        let cups = vec![cup(4, vec![LiquidColors::Red; 4]), cup(4, vec![])];
+        assert_eq!(solve(&cups, &UniqueMonochrome, &Standard), Some(vec![]));
+    }
+
+    #[test]
+    fn finds_shortest_solution() {
+        // Red on top of green in the first cup, the rest of the
+        // green in the second cup and a spare empty cup
+        let cups = vec![
+            cup(4, vec![LiquidColors::Green, LiquidColors::Red, LiquidColors::Red]),
+            cup(4, vec![LiquidColors::Green, LiquidColors::Green]),
+            cup(4, vec![]),
+        ];
+        assert_eq!(solve(&cups, &UniqueMonochrome, &Standard), Some(vec![(0, 2), (0, 1)]));
+    }
+
+    #[test]
+    fn unsolvable_board() {
+        let cups = vec![
+            cup(
+                4,
+                vec![
+                    LiquidColors::Red,
+                    LiquidColors::Green,
+                    LiquidColors::Red,
+                    LiquidColors::Green,
+                ],
+            ),
+            cup(
+                4,
+                vec![
+                    LiquidColors::Green,
+                    LiquidColors::Red,
+                    LiquidColors::Green,
+                    LiquidColors::Red,
+                ],
+            ),
+        ];
+        assert_eq!(solve(&cups, &UniqueMonochrome, &Standard), None);
+    }
+
+    #[test]
+    fn shuffle_pours_count_down_locks() {
+        // Moving the red over is pointless on its own, but it is the
+        // only pour that opens the lock on the rest of the red
+        let locked = Cup {
+            kind: CupKind::Locked(Lock::Pours(1)),
+            ..cup(4, vec![LiquidColors::Red; 2])
+        };
+        let cups = vec![cup(4, vec![LiquidColors::Red; 2]), cup(4, vec![]), locked];
+        let pours = solve(&cups, &UniqueMonochrome, &Standard).unwrap();
+        assert_eq!(pours[0], (0, 1));
+        assert_eq!(pours.len(), 2);
+    }
+
+    #[test]
+    fn goals_can_need_more_pours() {
+        // Already sorted, but the layout wants green in the first cup
+        let cups = vec![
+            cup(4, vec![LiquidColors::Green; 4]),
+            cup(4, vec![LiquidColors::Red; 4]),
+            cup(4, vec![]),
+        ];
+        let layout = TargetLayout {
+            target: vec![vec![LiquidColors::Red; 4], vec![LiquidColors::Green; 4], vec![]],
+        };
+        assert_eq!(solve(&cups, &layout, &Standard), Some(vec![(0, 2), (1, 0), (2, 1)]));
+    }
+
+    #[test]
+    fn only_cups_the_goal_needs_keep_their_place() {
+        let red = cup(4, vec![LiquidColors::Red]);
+        let green = cup(4, vec![LiquidColors::Green]);
+        let fill = FillCups {
+            targets: vec![(0, LiquidColors::Red)],
+        };
+        let key = |cups: &[Cup]| board_key(cups, &fill);
+        assert_eq!(
+            key(&[red.clone(), green.clone(), cup(4, vec![])]),
+            key(&[red.clone(), cup(4, vec![]), green.clone()])
+        );
+        assert_ne!(
+            key(&[red.clone(), green.clone(), cup(4, vec![])]),
+            key(&[green.clone(), red.clone(), cup(4, vec![])])
+        );
+        // Sandbox boards are never searched
+        assert_eq!(solve(&[red, green], &UniqueMonochrome, &Sandbox), None);
+    }
+
+    #[test]
+    fn single_unit_pours_take_longer() {
+        let cups = vec![
+            cup(4, vec![LiquidColors::Green, LiquidColors::Red, LiquidColors::Red]),
+            cup(4, vec![LiquidColors::Green, LiquidColors::Green]),
+            cup(4, vec![]),
+        ];
+        assert_eq!(solve(&cups, &UniqueMonochrome, &SingleUnit).map(|p| p.len()), Some(3));
+    }
+
+    #[test]
+    fn explores_every_board() {
+        let cups = vec![
+            cup(4, vec![LiquidColors::Green, LiquidColors::Red]),
+            cup(4, vec![LiquidColors::Red, LiquidColors::Green]),
+            cup(4, vec![]),
+        ];
+        let space = explore(&cups, &UniqueMonochrome, &Standard, 1000);
+        assert!(space.is_complete());
+        assert!(space.boards.iter().any(|b| UniqueMonochrome.is_won(b)));
+        assert!(space.dead_ends(&UniqueMonochrome).is_empty());
+
+        let stuck = vec![
+            cup(
+                4,
+                vec![
+                    LiquidColors::Red,
+                    LiquidColors::Green,
+                    LiquidColors::Red,
+                    LiquidColors::Green,
+                ],
+            ),
+            cup(
+                4,
+                vec![
+                    LiquidColors::Green,
+                    LiquidColors::Red,
+                    LiquidColors::Green,
+                    LiquidColors::Red,
+                ],
+            ),
+        ];
+        let space = explore(&stuck, &UniqueMonochrome, &Standard, 1000);
+        assert_eq!(space.dead_ends(&UniqueMonochrome), vec![0]);
+        assert_eq!(space.shortest_path(&UniqueMonochrome), None);
+    }
+
+    #[test]
+    fn finds_dead_ends() {
+        // The red on top of the full cup can still be poured, but after
+        // that there is nowhere left to go
+        let stuck = vec![
+            cup(4, vec![LiquidColors::Red, LiquidColors::Green, LiquidColors::Red]),
+            cup(
+                4,
+                vec![
+                    LiquidColors::Green,
+                    LiquidColors::Red,
+                    LiquidColors::Green,
+                    LiquidColors::Red,
+                ],
+            ),
+        ];
+        assert!(!legal_pours(&stuck, &Standard).is_empty());
+        assert_eq!(is_dead_end(&stuck, &UniqueMonochrome, &Standard), Some(true));
+
+        let cups = vec![
+            cup(4, vec![LiquidColors::Green, LiquidColors::Red, LiquidColors::Red]),
+            cup(4, vec![LiquidColors::Green, LiquidColors::Green]),
+            cup(4, vec![]),
+        ];
+        assert_eq!(is_dead_end(&cups, &UniqueMonochrome, &Standard), Some(false));
+    }
+
+    #[test]
+    fn explores_to_a_depth() {
+        let cups = vec![
+            cup(4, vec![LiquidColors::Green, LiquidColors::Red, LiquidColors::Red]),
+            cup(4, vec![LiquidColors::Green, LiquidColors::Green]),
+            cup(4, vec![]),
+        ];
+        let space = explore_to_depth(&cups, &UniqueMonochrome, &Standard, 1000, 1);
+        assert_eq!(space.expanded, 1);
+        assert!(space.depths[1..].iter().all(|d| *d == 1));
+
+        let space = explore(&cups, &UniqueMonochrome, &Standard, 1000);
+        let path = space.shortest_path(&UniqueMonochrome).unwrap();
+        // Same length as the solver's solution
+        assert_eq!(path.len(), 3);
+        assert_eq!(path[0], 0);
+        assert!(UniqueMonochrome.is_won(&space.boards[path[2]]));
+    }
+
+    #[test]
+    fn hints_stop_at_the_next_reveal() {
+        // The red under the hidden layer is only uncovered once the green
+        // on top of it has somewhere to go
+        let mut mystery = cup(4, vec![LiquidColors::Red, LiquidColors::Green, LiquidColors::Green]);
+        mystery.hidden = 2;
+        let cups = vec![
+            mystery,
+            cup(4, vec![LiquidColors::Red, LiquidColors::Red]),
+            cup(4, vec![]),
+        ];
+        assert_eq!(
+            next_reveal(&cups, &UniqueMonochrome, &Standard),
+            Some(vec![(0, 2)])
+        );
+    }
+}
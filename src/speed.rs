@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+// How fast the solver and replays play, + and - move between them
+const SPEEDS: [(f64, &str); 5] = [
+    (0.25, "¼x"),
+    (0.5, "½x"),
+    (1.0, "1x"),
+    (2.0, "2x"),
+    (4.0, "4x"),
+];
+const DEFAULT_SPEED: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Speed {
+    // Index into SPEEDS
+    index: usize,
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Speed {
+            index: DEFAULT_SPEED,
+        }
+    }
+}
+
+impl Speed {
+    pub fn faster(&mut self) {
+        self.index = (self.index + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.index = self.index.saturating_sub(1);
+    }
+
+    // A wait as long as it would be at 1x, shortened or stretched
+    pub fn scale(&self, wait: Duration) -> Duration {
+        wait.div_f64(SPEEDS[self.index].0)
+    }
+
+    pub fn label(&self) -> &'static str {
+        SPEEDS[self.index].1
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+
+use crate::headless::json_string;
+use crate::level::{Level, level_to_string, share_code};
+use crate::solver::{Pour, StateSpace};
+use crate::win_condition::WinCondition;
+
+// Every board reachable from a starting board and the pours between
+// them, for studying how a level plays out. Boards with their cups in
+// a different order count as the same board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    // Graphviz, `dot -Tsvg states.dot > states.svg`
+    Dot,
+    Json,
+}
+
+// Picked from the file being written, DOT unless it ends in .json
+pub fn graph_format_for_path(path: &str) -> GraphFormat {
+    match path.ends_with(".json") {
+        true => GraphFormat::Json,
+        false => GraphFormat::Dot,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StateGraph {
+    pub space: StateSpace,
+    pub won: Vec<bool>,
+    pub dead_ends: Vec<bool>,
+    // Boards along the shortest solution, empty if none was found
+    pub solution: Vec<usize>,
+}
+
+impl StateGraph {
+    pub fn new(space: StateSpace, goal: &dyn WinCondition) -> StateGraph {
+        let won = space.boards.iter().map(|b| goal.is_won(b)).collect();
+        let mut dead_ends = vec![false; space.boards.len()];
+        for i in space.dead_ends(goal) {
+            dead_ends[i] = true;
+        }
+        let solution = space.shortest_path(goal).unwrap_or_default();
+        StateGraph {
+            space,
+            won,
+            dead_ends,
+            solution,
+        }
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.space.pours.iter().map(|p| p.len()).sum()
+    }
+
+    // Pours that lead to the same board are the same move, only the
+    // first is marked
+    fn is_solution_pour(&self, from: usize, pour: Pour, to: usize) -> bool {
+        self.solution.windows(2).any(|w| w == [from, to])
+            && self.space.pours[from].iter().find(|(_, next)| *next == to) == Some(&(pour, to))
+    }
+
+    // Every pour as the board it leaves, the pour and the board it
+    // reaches
+    fn edges(&self) -> impl Iterator<Item = (usize, Pour, usize)> + '_ {
+        self.space
+            .pours
+            .iter()
+            .enumerate()
+            .flat_map(|(from, pours)| pours.iter().map(move |(pour, to)| (from, *pour, *to)))
+    }
+
+    fn level(&self, idx: usize) -> Level {
+        Level {
+            cups: self.space.boards[idx].clone(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut lines = vec![
+            "digraph states {".to_string(),
+            format!("    // {}", self),
+            "    node [shape=box, fontname=\"monospace\"];".to_string(),
+        ];
+        for idx in 0..self.space.boards.len() {
+            // One cup per line, like a level file
+            let label = level_to_string(&self.level(idx))
+                .trim_end()
+                .replace('\n', "\\n");
+            let mut attributes = vec![format!("label=\"{}\"", label)];
+            if idx == 0 {
+                attributes.push("penwidth=3".to_string());
+            }
+            if self.won[idx] {
+                attributes.push("style=filled, fillcolor=palegreen".to_string());
+            } else if self.dead_ends[idx] {
+                attributes.push("style=filled, fillcolor=lightpink".to_string());
+            } else if idx >= self.space.expanded {
+                attributes.push("style=dashed".to_string());
+            }
+            lines.push(format!("    {} [{}];", idx, attributes.join(", ")));
+        }
+        for (from, (src, dst), to) in self.edges() {
+            let mut attributes = vec![format!("label=\"{} → {}\"", src + 1, dst + 1)];
+            if self.is_solution_pour(from, (src, dst), to) {
+                attributes.push("color=blue, penwidth=2".to_string());
+            }
+            lines.push(format!(
+                "    {} -> {} [{}];",
+                from,
+                to,
+                attributes.join(", ")
+            ));
+        }
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+
+    // Boards are share codes and pours number the cups from 1
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<String> = (0..self.space.boards.len())
+            .map(|idx| {
+                format!(
+                    "{{\"id\":{},\"depth\":{},\"board\":{},\"won\":{},\"dead_end\":{},\"explored\":{}}}",
+                    idx,
+                    self.space.depths[idx],
+                    json_string(&share_code(&self.level(idx))),
+                    self.won[idx],
+                    self.dead_ends[idx],
+                    idx < self.space.expanded
+                )
+            })
+            .collect();
+        let edges: Vec<String> = self
+            .edges()
+            .map(|(from, (src, dst), to)| {
+                format!(
+                    "{{\"from\":{},\"to\":{},\"pour\":[{},{}],\"solution\":{}}}",
+                    from,
+                    to,
+                    src + 1,
+                    dst + 1,
+                    self.is_solution_pour(from, (src, dst), to)
+                )
+            })
+            .collect();
+        let solution: Vec<String> = self.solution.iter().map(|i| i.to_string()).collect();
+        format!(
+            "{{\"nodes\":[{}],\"edges\":[{}],\"solution\":[{}],\"complete\":{}}}\n",
+            nodes.join(","),
+            edges.join(","),
+            solution.join(","),
+            self.space.is_complete()
+        )
+    }
+
+    pub fn to_format(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+}
+
+impl fmt::Display for StateGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let solution = match self.solution.len() {
+            0 => "no solution found".to_string(),
+            n => format!("solved in {} pours", n - 1),
+        };
+        let stopped = match self.space.is_complete() {
+            true => "",
+            false => ", stopped at the limit",
+        };
+        write!(
+            f,
+            "{} boards, {} pours, {} dead ends, {}{}",
+            self.space.boards.len(),
+            self.edge_count(),
+            self.dead_ends.iter().filter(|d| **d).count(),
+            solution,
+            stopped
+        )
+    }
+}
+
+#[cfg(test)]
+mod test_state_graph {
+    use super::*;
+    use crate::level::parse_level;
+    use crate::pour_rule::Standard;
+    use crate::solver::{explore, explore_to_depth};
+    use crate::win_condition::UniqueMonochrome;
+
+    #[test]
+    fn marks_the_solution_and_dead_ends() {
+        let cups = parse_level("3 green red red\n3 green green\n3")
+            .unwrap()
+            .cups;
+        let graph = StateGraph::new(
+            explore(&cups, &UniqueMonochrome, &Standard, 1000),
+            &UniqueMonochrome,
+        );
+        assert_eq!(graph.solution.len(), 3);
+        assert!(graph.to_string().ends_with("solved in 2 pours"));
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph states {\n"));
+        assert!(dot.contains("    0 [label=\"3 green red red\\n3 green green\\n3\", penwidth=3];"));
+        assert_eq!(dot.matches("color=blue").count(), 2);
+        assert_eq!(dot.matches(" -> ").count(), graph.edge_count());
+
+        let json = graph.to_json();
+        assert!(json.starts_with(
+            "{\"nodes\":[{\"id\":0,\"depth\":0,\"board\":\"3.green.red.red/3.green.green/3\","
+        ));
+        assert_eq!(json.matches("\"solution\":true").count(), 2);
+        assert!(json.ends_with("\"complete\":true}\n"));
+    }
+
+    #[test]
+    fn stops_at_the_depth_limit() {
+        let cups = parse_level("3 green red red\n3 green green\n3")
+            .unwrap()
+            .cups;
+        let graph = StateGraph::new(
+            explore_to_depth(&cups, &UniqueMonochrome, &Standard, 1000, 1),
+            &UniqueMonochrome,
+        );
+        assert!(graph.solution.is_empty());
+        assert!(
+            graph
+                .to_string()
+                .ends_with("no solution found, stopped at the limit")
+        );
+        assert!(graph.to_dot().contains("style=dashed"));
+        assert_eq!(graph_format_for_path("states.json"), GraphFormat::Json);
+        assert_eq!(graph_format_for_path("states.gv"), GraphFormat::Dot);
+    }
+}
@@ -0,0 +1,255 @@
+use std::{io, time::Duration};
+
+use crate::daily::{today, DailyRecord, DailyStats};
+use crate::level_creator::SimpleDifficulties;
+use crate::storage::{append_line, read_lines};
+
+const STATS_FILE: &str = "stats.log";
+// How many of the latest solve times to keep for the sparkline
+const RECENT_SOLVES: usize = 20;
+
+// Every game is stored as its own line appended to the stats file,
+// and the totals are rebuilt from the lines when read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+    pub difficulty: SimpleDifficulties,
+    pub won: bool,
+    pub moves: usize,
+    pub time_taken: Duration,
+    pub units_poured: usize,
+}
+
+impl GameRecord {
+    pub fn to_line(&self) -> String {
+        format!(
+            "game difficulty={} won={} moves={} secs={} poured={}",
+            self.difficulty,
+            self.won as u8,
+            self.moves,
+            self.time_taken.as_secs(),
+            self.units_poured
+        )
+    }
+
+    // Lines that are not games, or that can not be read, are skipped
+    // so an old or hand edited file never stops the game from loading
+    pub fn from_line(line: &str) -> Option<GameRecord> {
+        let mut words = line.split_whitespace();
+        if words.next()? != "game" {
+            return None;
+        }
+        let mut difficulty = None;
+        let mut won = None;
+        let mut moves = None;
+        let mut secs = None;
+        let mut poured = None;
+        for word in words {
+            let (key, value) = word.split_once('=')?;
+            match key {
+                "difficulty" => difficulty = SimpleDifficulties::from_name(value),
+                "won" => won = Some(value == "1"),
+                "moves" => moves = value.parse().ok(),
+                "secs" => secs = value.parse().ok(),
+                "poured" => poured = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(GameRecord {
+            difficulty: difficulty?,
+            won: won?,
+            moves: moves?,
+            time_taken: Duration::from_secs(secs?),
+            units_poured: poured?,
+        })
+    }
+}
+
+// How far an endless run got before it ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndlessRecord {
+    pub depth: usize,
+    pub score: usize,
+}
+
+impl EndlessRecord {
+    pub fn to_line(&self) -> String {
+        format!("endless depth={} score={}", self.depth, self.score)
+    }
+
+    pub fn from_line(line: &str) -> Option<EndlessRecord> {
+        let mut words = line.split_whitespace();
+        if words.next()? != "endless" {
+            return None;
+        }
+        let mut depth = None;
+        let mut score = None;
+        for word in words {
+            let (key, value) = word.split_once('=')?;
+            match key {
+                "depth" => depth = value.parse().ok(),
+                "score" => score = value.parse().ok(),
+                _ => {}
+            }
+        }
+        Some(EndlessRecord {
+            depth: depth?,
+            score: score?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DifficultyStats {
+    pub played: usize,
+    pub won: usize,
+    // Summed over won games only, used for the average
+    pub total_moves: usize,
+    pub fastest: Option<Duration>,
+}
+
+impl DifficultyStats {
+    pub fn average_moves(&self) -> Option<f64> {
+        if self.won == 0 {
+            return None;
+        }
+        Some(self.total_moves as f64 / self.won as f64)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub easy: DifficultyStats,
+    pub medium: DifficultyStats,
+    pub hard: DifficultyStats,
+    pub current_streak: usize,
+    pub best_streak: usize,
+    pub units_poured: usize,
+    // Oldest first
+    pub recent_solve_times: Vec<Duration>,
+    pub daily: DailyStats,
+    pub best_endless_depth: usize,
+    pub best_endless_score: usize,
+}
+
+impl Stats {
+    pub fn from_records(records: &[GameRecord]) -> Stats {
+        let mut stats = Stats::default();
+        for record in records {
+            let diff_stats = stats.for_difficulty_mut(&record.difficulty);
+            diff_stats.played += 1;
+            if record.won {
+                diff_stats.won += 1;
+                diff_stats.total_moves += record.moves;
+                if diff_stats.fastest.is_none_or(|f| record.time_taken < f) {
+                    diff_stats.fastest = Some(record.time_taken);
+                }
+                stats.current_streak += 1;
+                stats.best_streak = stats.best_streak.max(stats.current_streak);
+                stats.recent_solve_times.push(record.time_taken);
+            } else {
+                stats.current_streak = 0;
+            }
+            stats.units_poured += record.units_poured;
+        }
+        let skip = stats.recent_solve_times.len().saturating_sub(RECENT_SOLVES);
+        stats.recent_solve_times.drain(..skip);
+        stats
+    }
+
+    pub fn for_difficulty(&self, diff: &SimpleDifficulties) -> &DifficultyStats {
+        match diff {
+            SimpleDifficulties::Easy => &self.easy,
+            SimpleDifficulties::Medium => &self.medium,
+            SimpleDifficulties::Hard => &self.hard,
+        }
+    }
+
+    fn for_difficulty_mut(&mut self, diff: &SimpleDifficulties) -> &mut DifficultyStats {
+        match diff {
+            SimpleDifficulties::Easy => &mut self.easy,
+            SimpleDifficulties::Medium => &mut self.medium,
+            SimpleDifficulties::Hard => &mut self.hard,
+        }
+    }
+}
+
+pub fn record_game(record: &GameRecord) -> io::Result<()> {
+    append_line(STATS_FILE, &record.to_line())
+}
+
+pub fn record_daily(record: &DailyRecord) -> io::Result<()> {
+    append_line(STATS_FILE, &record.to_line())
+}
+
+pub fn record_endless(record: &EndlessRecord) -> io::Result<()> {
+    append_line(STATS_FILE, &record.to_line())
+}
+
+pub fn load_stats() -> io::Result<Stats> {
+    let lines = read_lines(STATS_FILE)?;
+    let games: Vec<GameRecord> = lines.iter().filter_map(|l| GameRecord::from_line(l)).collect();
+    let dailies: Vec<DailyRecord> = lines.iter().filter_map(|l| DailyRecord::from_line(l)).collect();
+    let mut stats = Stats::from_records(&games);
+    stats.daily = DailyStats::from_records(&dailies, today());
+    for run in lines.iter().filter_map(|l| EndlessRecord::from_line(l)) {
+        stats.best_endless_depth = stats.best_endless_depth.max(run.depth);
+        stats.best_endless_score = stats.best_endless_score.max(run.score);
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test_stats {
+    use super::*;
+
+    fn record(won: bool, moves: usize, secs: u64) -> GameRecord {
+        GameRecord {
+            difficulty: SimpleDifficulties::Hard,
+            won,
+            moves,
+            time_taken: Duration::from_secs(secs),
+            units_poured: moves * 2,
+        }
+    }
+
+    #[test]
+    fn line_round_trip() {
+        let r = record(true, 31, 95);
+        assert_eq!(GameRecord::from_line(&r.to_line()), Some(r));
+    }
+
+    #[test]
+    fn endless_line_round_trip() {
+        let r = EndlessRecord {
+            depth: 7,
+            score: 1250,
+        };
+        assert_eq!(EndlessRecord::from_line(&r.to_line()), Some(r));
+        assert_eq!(EndlessRecord::from_line(&record(true, 1, 1).to_line()), None);
+    }
+
+    #[test]
+    fn bad_lines_are_skipped() {
+        assert_eq!(GameRecord::from_line(""), None);
+        assert_eq!(GameRecord::from_line("game difficulty=hard won=1"), None);
+        assert_eq!(GameRecord::from_line("something else entirely"), None);
+    }
+
+    #[test]
+    fn totals_and_streaks() {
+        let stats = Stats::from_records(&[
+            record(true, 30, 100),
+            record(true, 20, 80),
+            record(false, 5, 10),
+            record(true, 40, 120),
+        ]);
+        assert_eq!(stats.hard.played, 4);
+        assert_eq!(stats.hard.won, 3);
+        assert_eq!(stats.hard.average_moves(), Some(30.0));
+        assert_eq!(stats.hard.fastest, Some(Duration::from_secs(80)));
+        assert_eq!(stats.current_streak, 1);
+        assert_eq!(stats.best_streak, 2);
+        assert_eq!(stats.units_poured, 190);
+        assert_eq!(stats.easy, DifficultyStats::default());
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+// Everything the game saves is kept as lines appended to small text files.
+// Appending a single short line is atomic, so several instances of the
+// game can write to the same file without locking or clobbering each other.
+
+// Files live in $TERMINAL_BARISTA_DATA_DIR when it is set, otherwise in
+// the platform's usual spot for per-user application data
+pub fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("TERMINAL_BARISTA_DATA_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return Some(PathBuf::from(dir).join("terminal_barista"));
+    }
+    if let Some(dir) = env::var_os("APPDATA") {
+        return Some(PathBuf::from(dir).join("terminal_barista"));
+    }
+    let home = env::var_os("HOME")?;
+    Some(PathBuf::from(home).join(".local/share/terminal_barista"))
+}
+
+pub fn append_line(file_name: &str, line: &str) -> io::Result<()> {
+    let dir = data_dir().ok_or_else(|| io::Error::other("No data directory found"))?;
+    fs::create_dir_all(&dir)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(file_name))?;
+    // One write call per line keeps concurrent appends from interleaving
+    file.write_all(format!("{}\n", line).as_bytes())
+}
+
+// Writes a file of its own into a folder next to the stats. Files are
+// named down to the millisecond, and a number is added when two land
+// in the same one, so a new file never replaces an old one.
+pub fn save_to_data_dir(folder: &str, prefix: &str, contents: &str) -> Result<PathBuf, String> {
+    let dir = data_dir().ok_or("No data directory found")?.join(folder);
+    fs::create_dir_all(&dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    for n in 0.. {
+        let path = match n {
+            0 => dir.join(format!("{}-{}.txt", prefix, millis)),
+            n => dir.join(format!("{}-{}-{}.txt", prefix, millis, n)),
+        };
+        let file = fs::OpenOptions::new().write(true).create_new(true).open(&path);
+        match file {
+            Ok(mut file) => {
+                file.write_all(contents.as_bytes())
+                    .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Could not write {}: {}", path.display(), e)),
+        }
+    }
+    unreachable!()
+}
+
+pub fn read_lines(file_name: &str) -> io::Result<Vec<String>> {
+    let Some(dir) = data_dir() else {
+        return Ok(vec![]);
+    };
+    match fs::read_to_string(dir.join(file_name)) {
+        Ok(contents) => Ok(contents.lines().map(|l| l.to_string()).collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
@@ -1,11 +1,29 @@
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Style, Stylize},
-    widgets::{Block, BorderType, Borders, Padding, Paragraph},
+    symbols,
+    text::{Line, Span},
+    widgets::{
+        BarChart, Block, BorderType, Borders, Clear, LineGauge, Padding, Paragraph, Row,
+        Sparkline, Table,
+    },
     Frame,
 };
 
-use crate::app::{App, CurrentScreen};
+use std::time::{Duration, Instant};
+
+use crate::app::{App, CurrentScreen, GameMode, MenuItem, MENU_ITEMS};
+use crate::daily::{format_day, today};
+use crate::highscores::{difficulty_leaderboard, level_leaderboard, HighScore};
+use crate::level_creator::SimpleDifficulties;
+use crate::cup::{CupKind, Lock};
+use crate::level::liquid_to_token;
+use crate::liquids::LiquidColors;
+use crate::pour_rule::{PourRule, Standard};
+use crate::orders::{ingredient_name, is_tap, Shift, TAPS};
+use crate::solver::legal_pours;
+
+const EDITOR_HELP: &str = "←/→ cup  ↑/↓ color  Enter paint  ⌫ erase  i ice  t cup type\na/d add/remove cup  +/- size  v check  w save  c share code  p play  Esc menu";
 
 pub fn ui(frame: &mut Frame, app: &App) {
     let root = Layout::default()
@@ -27,25 +45,159 @@ pub fn ui(frame: &mut Frame, app: &App) {
         .alignment(Alignment::Center);
     frame.render_widget(header_text, vertical_layout[0]);
 
-    let parsed_tooltip = match &app.tooltip {
-        Some(str) => str,
-        None => "",
+    let par_text = match app.par {
+        Some(par) => par.to_string(),
+        None => "?".to_string(),
+    };
+    // The welcome screen has no board to describe
+    if !app.cups.is_empty() {
+        let board_name = match app.mode {
+            _ if app.playback.is_some() => format!("Replay of board #{}", app.seed),
+            GameMode::Classic => format!("{} board #{}", app.difficulty, app.seed),
+            GameMode::Daily(day) => format!("Daily Blend {}", format_day(day)),
+            GameMode::Timed => {
+                format!("Time Attack {} board {}", app.difficulty, app.boards_cleared + 1)
+            }
+            GameMode::Limited => format!("Limited {} board #{}", app.difficulty, app.seed),
+            GameMode::Endless => format!(
+                "Endless board {}  Score: {}",
+                app.boards_cleared + 1,
+                app.score
+            ),
+            GameMode::Mystery => format!("Mystery {} board #{}", app.difficulty, app.seed),
+            GameMode::Custom if app.current_screen == CurrentScreen::Editor => {
+                "Level editor".to_string()
+            }
+            GameMode::Custom => "Custom level".to_string(),
+            GameMode::Orders => format!(
+                "Coffee Shop shift #{}  Score: {}",
+                app.seed,
+                app.shift.as_ref().map(|s| s.score()).unwrap_or(0)
+            ),
+        };
+        let board_text = Paragraph::new(board_name)
+            .block(Block::new().padding(Padding::new(2, 0, vertical_layout[2].height / 2, 0)))
+            .alignment(Alignment::Left);
+        frame.render_widget(board_text, vertical_layout[0]);
+
+        let moves_count = match app.moves_left() {
+            Some(left) => format!("Moves left: {}", left),
+            None => format!("Moves: {}", app.moves),
+        };
+        let moves_status = match &app.shift {
+            Some(shift) => format!(
+                "Served: {}  Walkouts: {}",
+                shift.served.len(),
+                shift.walkouts
+            ),
+            None => format!("{}  Par: {}", moves_count, par_text),
+        };
+        // The editor shows the liquid being painted with instead
+        let moves_status = match &app.editor {
+            Some(editor) if app.current_screen == CurrentScreen::Editor => Line::from(vec![
+                Span::from(format!("Paint: {} ", liquid_to_token(editor.paint_color()))),
+                Span::from("██").fg(editor.paint_color().to_color()),
+            ]),
+            _ => Line::from(moves_status),
+        };
+        let moves_text = Paragraph::new(moves_status)
+            .block(Block::new().padding(Padding::new(0, 2, vertical_layout[2].height / 2, 0)))
+            .alignment(Alignment::Right);
+        frame.render_widget(moves_text, vertical_layout[0]);
+    }
+
+    if let (Some(limit), Some(left)) = (app.time_limit, app.time_left()) {
+        let ratio = left.as_secs_f64() / limit.as_secs_f64();
+        let clock_color = if ratio < 0.1 {
+            Color::LightRed
+        } else if ratio < 0.3 {
+            Color::Yellow
+        } else {
+            Color::LightGreen
+        };
+        let header = vertical_layout[0];
+        let clock_width = header.width.min(40);
+        let clock_area = Rect {
+            x: header.x + (header.width - clock_width) / 2,
+            y: header.y + header.height.saturating_sub(1),
+            width: clock_width,
+            height: 1,
+        };
+        let clock = LineGauge::default()
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(format!("⏱ {}", format_duration(left)))
+            .filled_style(Style::new().fg(clock_color))
+            .line_set(symbols::line::THICK);
+        frame.render_widget(clock, clock_area);
+    }
+
+    // With nothing else to say the footer reminds the player of the
+    // goal, and of the rules if they are not the usual ones
+    let goal_text = if app.rule.name() == Standard.name() {
+        app.goal.describe()
+    } else {
+        format!("{}  ·  {}", app.goal.describe(), app.rule.describe())
+    };
+    let status_text = match (&app.autoplay, &app.playback) {
+        (Some(autoplay), _) => Some(format!(
+            "{}  ·  Space pause  n step  +/- speed  any other key takes over",
+            autoplay.status()
+        )),
+        (None, Some(playback)) => Some(format!(
+            "{}  ·  Space pause  ←/→ step  Home/End jump  +/- speed  q quit",
+            playback.status()
+        )),
+        (None, None) => None,
+    };
+    let parsed_tooltip = match (&app.tooltip, &status_text) {
+        (Some(str), _) => str,
+        (None, Some(str)) if app.current_screen == CurrentScreen::Main => str,
+        _ if app.current_screen == CurrentScreen::Main && app.mode != GameMode::Orders => {
+            &goal_text
+        }
+        _ if app.current_screen == CurrentScreen::Editor => EDITOR_HELP,
+        _ => "",
     };
     let footer_text = Paragraph::new(parsed_tooltip)
         .block(Block::new().padding(Padding::new(0, 0, vertical_layout[2].height / 2, 0)))
         .alignment(Alignment::Center);
     frame.render_widget(footer_text, vertical_layout[2]);
 
+    match app.current_screen {
+        CurrentScreen::Welcome => {
+            render_welcome(frame, app, vertical_layout[1]);
+            return;
+        }
+        CurrentScreen::Statistics => {
+            render_statistics(frame, app, vertical_layout[1]);
+            return;
+        }
+        CurrentScreen::HighScores => {
+            render_high_scores(frame, app, vertical_layout[1]);
+            return;
+        }
+        _ => {}
+    }
+
     let mut cup_top_margin_required = 0;
     while vertical_layout[0].height - cup_top_margin_required != 0 {
         cup_top_margin_required += 1;
     }
-    let middle_section_divisible_by_three = Rect {
+    let mut middle_section_divisible_by_three = Rect {
         x: vertical_layout[1].x,
         y: vertical_layout[1].y + cup_top_margin_required,
         width: vertical_layout[1].width,
         height: vertical_layout[1].height - cup_top_margin_required,
     };
+    // Waiting customers get a panel to the right of the counter
+    if let Some(shift) = &app.shift {
+        let counter = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(44)])
+            .split(middle_section_divisible_by_three);
+        middle_section_divisible_by_three = counter[0];
+        render_orders(frame, shift, counter[1]);
+    }
 
     let mut cup_items: Vec<Constraint> = vec![];
     for _ in 0..app.cups.len() {
@@ -59,44 +211,89 @@ pub fn ui(frame: &mut Frame, app: &App) {
         .constraints(cup_items)
         .split(middle_section_divisible_by_three);
 
+    // Smaller cups are shorter, so every cup sits on the same counter
+    // and units shrink when the tallest cup would not fit
+    let tallest = app.cups.iter().map(|c| c.capacity).max().unwrap_or(0);
+    let available = cups_layout.first().map_or(0, |r| r.height.saturating_sub(2));
+    let unit_height = (available / tallest.max(1) as u16).clamp(1, 5);
+    let mut cup_areas = vec![];
     let mut liquid_items = vec![];
     for i in 0..app.cups.len() {
+        let shorter_by =
+            ((tallest - app.cups[i].capacity) as u16 * unit_height).min(cups_layout[i].height);
+        let cup_area = Rect {
+            y: cups_layout[i].y + shorter_by,
+            height: cups_layout[i].height - shorter_by,
+            ..cups_layout[i]
+        };
         let mut liquids = vec![];
         for _ in 0..app.cups[i].capacity {
-            liquids.push(Constraint::Length(5));
+            liquids.push(Constraint::Length(unit_height));
         }
 
         let items = Layout::default()
             .direction(Direction::Vertical)
+            .flex(Flex::End)
             .constraints(liquids)
             .margin(1)
-            .split(cups_layout[i]);
+            .split(cup_area);
+        cup_areas.push(cup_area);
         liquid_items.push(items);
     }
 
     for (i, cup) in app.cups.iter().enumerate() {
+        let cup_label = match (app.mode, &cup.kind) {
+            (GameMode::Orders, _) if is_tap(i) => ingredient_name(&TAPS[i]),
+            (GameMode::Orders, _) => "serve",
+            (_, CupKind::OneWay) => "in only",
+            _ => "",
+        };
         let mut selected_i = false;
         if let Some(src_selection) = app.src_selection {
             selected_i = (i as u32) == src_selection;
         }
-        if selected_i {
+        if let Some(editor) = &app.editor
+            && app.current_screen == CurrentScreen::Editor
+        {
+            selected_i = i == editor.cursor;
+        }
+        // The cup the solver is about to pour into
+        let target_i = app
+            .autoplay
+            .as_ref()
+            .and_then(|autoplay| autoplay.pending)
+            .is_some_and(|(_, dst)| dst == i);
+        if target_i {
+            frame.render_widget(
+                Block::new()
+                    .title(cup_label)
+                    .title_bottom(format!("[{}]", i + 1))
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
+                    .border_type(BorderType::Rounded)
+                    .title_style(Style::new().bg(Color::Yellow).fg(Color::Black)),
+                cup_areas[i],
+            );
+        } else if selected_i {
             frame.render_widget(
                 Block::new()
+                    .title(cup_label)
                     .title_bottom(format!("[{}]", i + 1))
                     .title_alignment(Alignment::Center)
                     .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
                     .border_type(BorderType::Rounded)
                     .title_style(Style::new().bg(Color::White).fg(Color::Black)),
-                cups_layout[i],
+                cup_areas[i],
             );
         } else {
             frame.render_widget(
                 Block::new()
+                    .title(cup_label)
                     .title_bottom(format!("[{}]", i + 1))
                     .title_alignment(Alignment::Center)
                     .borders(Borders::BOTTOM | Borders::LEFT | Borders::RIGHT)
                     .border_type(BorderType::Rounded),
-                cups_layout[i],
+                cup_areas[i],
             );
         }
         for k in 0..cup.liquids.len() {
@@ -106,32 +303,420 @@ pub fn ui(frame: &mut Frame, app: &App) {
             // The liquids are displayed top to bottom, but
             // we want to display bottom to top. So we need
             // to render starting from the bottom
+            let liquid_area = liquid_items[i][max_index - k + offset];
+            if !cup.is_revealed(k) {
+                let mystery = Paragraph::new("? ? ?\n ? ? \n? ? ?")
+                    .alignment(Alignment::Center)
+                    .bg(Color::DarkGray)
+                    .fg(Color::Gray)
+                    .block(Block::new().padding(Padding::top(1)));
+                frame.render_widget(mystery, liquid_area);
+                continue;
+            }
+            // Special liquids get a label across the middle of the unit
+            let label = match liquid {
+                LiquidColors::Syrup => Some("≈ ≈ ≈".to_string()),
+                LiquidColors::Ice(_, moves) => Some(format!("ice {}", moves)),
+                LiquidColors::Shot => Some("shot".to_string()),
+                _ => None,
+            };
+            if let Some(label) = label {
+                let fg = match liquid {
+                    LiquidColors::Shot => Color::LightRed,
+                    LiquidColors::Syrup => Color::White,
+                    _ => Color::Black,
+                };
+                let special = Paragraph::new(label)
+                    .alignment(Alignment::Center)
+                    .bg(liquid.to_color())
+                    .fg(fg)
+                    .bold()
+                    .block(Block::new().padding(Padding::top(liquid_area.height / 2)));
+                frame.render_widget(special, liquid_area);
+                continue;
+            }
             frame.render_widget(
                 Block::new()
                     .bg(liquid.to_color())
                     .fg(Color::Black)
                     .padding(Padding::uniform(3)),
-                liquid_items[i][max_index - k + offset],
+                liquid_area,
             );
         }
+        // A locked cup has a lid on it showing what opens it
+        if let CupKind::Locked(lock) = &cup.kind {
+            let trigger = match lock {
+                Lock::Pours(pours) => Line::from(format!("{} pours", pours)),
+                Lock::Color(color) => Line::from(Span::from("████").fg(color.to_color())),
+            };
+            let lid = Paragraph::new(vec![Line::from("🔒"), trigger])
+                .alignment(Alignment::Center)
+                .bg(Color::DarkGray)
+                .fg(Color::White);
+            let lid_area = Rect {
+                height: cup_areas[i].height.min(2),
+                ..cup_areas[i]
+            };
+            frame.render_widget(Clear, lid_area);
+            frame.render_widget(lid, lid_area);
+        }
     }
 
-    if app.current_screen == CurrentScreen::Finished {
+    let popup_text = match app.current_screen {
+        CurrentScreen::Finished => Some(victory_text(app, &par_text)),
+        CurrentScreen::GameOver if app.mode == GameMode::Limited => Some(format!(
+            "Out of moves 🫗\n \nUsed all {} pours without finishing\n \nUndo [u]  Retry [r]\nNew Board [Enter]  Menu [Esc]",
+            app.moves
+        )),
+        CurrentScreen::GameOver if app.mode == GameMode::Endless => Some(format!(
+            "Run over 🏁\n \nBoards cleared: {}\nScore: {}\n \nNew Run [Enter]  Menu [Esc]",
+            app.boards_cleared, app.score
+        )),
+        CurrentScreen::Stuck => {
+            let reason = match legal_pours(&app.cups, app.rule.as_ref()).is_empty() {
+                true => "No pours left 🫠\n \nNone of the cups can be poured into another",
+                false => "Dead end 🚧\n \nThis board can't be finished from here",
+            };
+            Some(format!("{}\n \nUndo [u]  Restart [r]  Menu [Esc]", reason))
+        }
+        CurrentScreen::GameOver => Some(format!(
+            "Out of time ⌛\n \nBoards cleared: {}\nMoves on this board: {}\n \nNew Run [Enter]  Menu [Esc]",
+            app.boards_cleared, app.moves
+        )),
+        _ => None,
+    };
+    if let Some(popup_text) = popup_text {
         let parent_layout = middle_section_divisible_by_three;
         let victory_text_width = parsed_tooltip.chars().count();
         let victory_box_width = std::cmp::max((victory_text_width as u16) + 6, 60);
-        let victory_box_height = 7;
+        let victory_box_height = 11;
         let victory_box = Rect {
             x: parent_layout.x + (parent_layout.width / 2) - (victory_box_width / 2),
             y: parent_layout.y + (parent_layout.height / 2) - (victory_box_height / 2),
             width: victory_box_width,
             height: victory_box_height,
         };
-        let victory_text = Paragraph::new("You win 🥳 \n \n New Game [Enter]")
+        let victory_text = Paragraph::new(popup_text)
             .alignment(Alignment::Center)
-            .block(Block::new().padding(Padding::new(0, 0, victory_box.height / 2 - 1, 0)))
+            .block(Block::new().padding(Padding::new(0, 0, victory_box.height / 2 - 3, 0)))
             .bg(Color::White)
             .fg(Color::Black);
+        frame.render_widget(Clear, victory_box);
         frame.render_widget(victory_text, victory_box);
     }
 }
+
+fn victory_text(app: &App, par_text: &str) -> String {
+    let stars_text = match app.stars() {
+        Some(stars) => "★".repeat(stars) + &"☆".repeat(3 - stars),
+        None => "".to_string(),
+    };
+    let time_text = match app.time_taken {
+        Some(t) => format_duration(t),
+        None => "-".to_string(),
+    };
+    let prompt_text = match (&app.name_entry, app.mode) {
+        (Some(name), _) => {
+            format!("New high score! Name: {}_\nSave [Enter]  Skip [Esc]", name)
+        }
+        (None, GameMode::Custom) if app.editor.is_some() => "Back to the editor [Enter]".to_string(),
+        (None, GameMode::Custom) => "Menu [Enter]".to_string(),
+        (None, GameMode::Daily(_)) => {
+            "Your share card is printed when you quit [q]\nMenu [Enter]  High Scores [l]"
+                .to_string()
+        }
+        (None, GameMode::Timed) => format!(
+            "{} carried over\nNext Board [Enter]  High Scores [l]",
+            format_duration(app.time_left().unwrap_or_default())
+        ),
+        (None, GameMode::Endless) => format!(
+            "+{} points, {} total\nNext Board [Enter]  Give Up [g]",
+            app.board_points(),
+            app.score
+        ),
+        (None, GameMode::Classic | GameMode::Limited | GameMode::Mystery) => {
+            "New Game [Enter]  High Scores [l]".to_string()
+        }
+        (None, GameMode::Orders) => "New Shift [Enter]  Quit [q]".to_string(),
+    };
+    if let Some(shift) = &app.shift {
+        return format!(
+            "Shift over ☕️\n \nDrinks served: {}  Perfect: {}\nWalkouts: {}\nScore: {}\n \n{}",
+            shift.served.len(),
+            shift.perfect_drinks(),
+            shift.walkouts,
+            shift.score(),
+            prompt_text
+        );
+    }
+    format!(
+        "You win 🥳 \n{}\n \nMoves: {}  Par: {}\nTime: {}  Hints: {}\n \n{}",
+        stars_text, app.moves, par_text, time_text, app.hints_used, prompt_text
+    )
+}
+
+fn render_orders(frame: &mut Frame, shift: &Shift, area: Rect) {
+    let panel = Block::new()
+        .title(" Orders ")
+        .title_bottom(" Serve [Enter]  Dump [x] ")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::horizontal(1));
+    let inner = panel.inner(area);
+    frame.render_widget(panel, area);
+
+    let now = Instant::now();
+    let slots = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(shift.waiting.iter().map(|_| Constraint::Length(4)))
+        .split(inner);
+    for (order, slot) in shift.waiting.iter().zip(slots.iter()) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1); 3])
+            .split(*slot);
+        frame.render_widget(
+            Paragraph::new(format!("Customer {}: {}", order.customer, order.describe())),
+            rows[0],
+        );
+        // Layers read left to right from the bottom of the cup up
+        let layers: Vec<Span> = order
+            .recipe
+            .iter()
+            .map(|layer| Span::styled("   ", Style::new().bg(layer.to_color())))
+            .flat_map(|block| [block, Span::raw(" ")])
+            .collect();
+        frame.render_widget(Paragraph::new(Line::from(layers)), rows[1]);
+
+        let left = order.patience_left(now);
+        let ratio = left.as_secs_f64() / order.patience.as_secs_f64();
+        let mood = if ratio < 0.25 {
+            Color::LightRed
+        } else if ratio < 0.5 {
+            Color::Yellow
+        } else {
+            Color::LightGreen
+        };
+        let patience = LineGauge::default()
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(format_duration(left))
+            .filled_style(Style::new().fg(mood))
+            .line_set(symbols::line::THICK);
+        frame.render_widget(patience, rows[2]);
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+fn render_statistics(frame: &mut Frame, app: &App, area: Rect) {
+    let outer = Block::new()
+        .title(" Statistics ")
+        .title_bottom(" Back [Esc] ")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::uniform(1));
+    let inner = outer.inner(area);
+    frame.render_widget(outer, area);
+
+    let Some(stats) = &app.stats else {
+        frame.render_widget(
+            Paragraph::new("Could not read the stats file :(").alignment(Alignment::Center),
+            inner,
+        );
+        return;
+    };
+
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(5),
+            Constraint::Length(3),
+            Constraint::Min(6),
+        ])
+        .split(inner);
+
+    let rows = SimpleDifficulties::ALL.iter().map(|diff| {
+        let diff_stats = stats.for_difficulty(diff);
+        Row::new(vec![
+            diff.to_string(),
+            diff_stats.played.to_string(),
+            diff_stats.won.to_string(),
+            match diff_stats.average_moves() {
+                Some(avg) => format!("{:.1}", avg),
+                None => "-".to_string(),
+            },
+            match diff_stats.fastest {
+                Some(t) => format_duration(t),
+                None => "-".to_string(),
+            },
+        ])
+    });
+    let table = Table::new(rows, [Constraint::Fill(1); 5])
+        .header(Row::new(vec!["", "Played", "Won", "Avg moves", "Fastest"]).bold());
+    frame.render_widget(table, sections[0]);
+
+    let totals = Paragraph::new(format!(
+        "Win streak: {}  Best streak: {}  Daily streak: {}  Best daily streak: {}\nBest endless run: {} boards ({} points)  Liquid poured: {} units",
+        stats.current_streak,
+        stats.best_streak,
+        stats.daily.streak,
+        stats.daily.best_streak,
+        stats.best_endless_depth,
+        stats.best_endless_score,
+        stats.units_poured
+    ))
+    .alignment(Alignment::Center);
+    frame.render_widget(totals, sections[1]);
+
+    let charts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(sections[2]);
+
+    let wins: Vec<(String, u64)> = SimpleDifficulties::ALL
+        .iter()
+        .map(|diff| (diff.to_string(), stats.for_difficulty(diff).won as u64))
+        .collect();
+    let wins: Vec<(&str, u64)> = wins.iter().map(|(d, w)| (d.as_str(), *w)).collect();
+    let bar_chart = BarChart::default()
+        .block(Block::new().title("Wins").borders(Borders::ALL))
+        .bar_width(8)
+        .bar_gap(2)
+        .bar_style(Style::new().fg(Color::LightGreen))
+        .data(&wins);
+    frame.render_widget(bar_chart, charts[0]);
+
+    let solve_times: Vec<u64> = stats
+        .recent_solve_times
+        .iter()
+        .map(|t| t.as_secs())
+        .collect();
+    let sparkline = Sparkline::default()
+        .block(
+            Block::new()
+                .title("Recent solve times")
+                .borders(Borders::ALL),
+        )
+        .style(Style::new().fg(Color::Yellow))
+        .data(&solve_times);
+    frame.render_widget(sparkline, charts[1]);
+}
+
+fn render_high_scores(frame: &mut Frame, app: &App, area: Rect) {
+    let outer = Block::new()
+        .title(" High Scores ")
+        .title_bottom(" Back [Esc] ")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::uniform(1));
+    let inner = outer.inner(area);
+    frame.render_widget(outer, area);
+
+    let boards = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(inner);
+
+    render_leaderboard(
+        frame,
+        format!("This board (#{})", app.seed),
+        level_leaderboard(&app.high_scores, app.difficulty, app.seed),
+        boards[0],
+    );
+    render_leaderboard(
+        frame,
+        format!("All {} boards", app.difficulty),
+        difficulty_leaderboard(&app.high_scores, app.difficulty),
+        boards[1],
+    );
+}
+
+fn render_leaderboard(frame: &mut Frame, title: String, scores: Vec<&HighScore>, area: Rect) {
+    let rows = scores.iter().enumerate().map(|(i, score)| {
+        Row::new(vec![
+            format!("{}.", i + 1),
+            score.name.clone(),
+            score.moves.to_string(),
+            format_duration(score.time_taken),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(4),
+            Constraint::Fill(1),
+            Constraint::Length(6),
+            Constraint::Length(6),
+        ],
+    )
+    .header(Row::new(vec!["", "Name", "Moves", "Time"]).bold())
+    .block(
+        Block::new()
+            .title(title)
+            .borders(Borders::ALL)
+            .padding(Padding::horizontal(1)),
+    );
+    frame.render_widget(table, area);
+}
+
+fn render_welcome(frame: &mut Frame, app: &App, area: Rect) {
+    let menu_width = 56;
+    let menu_height = 12;
+    let menu_area = Rect {
+        x: area.x + area.width.saturating_sub(menu_width) / 2,
+        y: area.y + area.height.saturating_sub(menu_height) / 2,
+        width: menu_width.min(area.width),
+        height: menu_height.min(area.height),
+    };
+    let menu = Block::new()
+        .title(" Pick a blend ")
+        .title_bottom(" Start [Enter]  Stats [s]  Quit [q] ")
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .padding(Padding::uniform(1));
+    let inner = menu.inner(menu_area);
+    frame.render_widget(menu, menu_area);
+
+    let daily_stats = app.stats.as_ref().map(|s| s.daily.clone()).unwrap_or_default();
+    let rows = MENU_ITEMS.iter().enumerate().map(|(i, item)| {
+        let (name, detail) = match item {
+            MenuItem::Classic => ("Classic", format!("◀ {} ▶", app.difficulty)),
+            MenuItem::Timed => ("Time Attack", format!("◀ {} ▶", app.difficulty)),
+            MenuItem::Limited => ("Limited Pours", format!("◀ {} ▶", app.difficulty)),
+            MenuItem::Mystery => ("Mystery", format!("◀ {} ▶", app.difficulty)),
+            MenuItem::Orders => ("Coffee Shop", "serve the customers".to_string()),
+            MenuItem::Editor => ("Level Editor", "build your own board".to_string()),
+            MenuItem::Endless => {
+                let best = app.stats.as_ref().map(|s| s.best_endless_depth).unwrap_or(0);
+                ("Endless", format!("best run {} boards", best))
+            }
+            MenuItem::Daily => {
+                let done = if daily_stats.completed_today { " ✓" } else { "" };
+                (
+                    "Daily Blend",
+                    format!(
+                        "{}{}  streak {}",
+                        format_day(today()),
+                        done,
+                        daily_stats.streak
+                    ),
+                )
+            }
+        };
+        let row = Row::new(vec![name.to_string(), detail]);
+        if i == app.menu_selection {
+            row.style(Style::new().bg(Color::White).fg(Color::Black))
+        } else {
+            row
+        }
+    });
+    let table = Table::new(rows, [Constraint::Length(14), Constraint::Fill(1)]);
+    frame.render_widget(table, inner);
+}
@@ -0,0 +1,329 @@
+use std::fmt::Debug;
+
+use crate::cup::{are_cups_solved, Cup};
+use crate::liquids::LiquidColors;
+
+// Decides when a board counts as finished. The solver uses the same
+// condition, so par and hints always aim for the goal being played.
+pub trait WinCondition: Debug {
+    fn is_won(&self, cups: &[Cup]) -> bool;
+
+    // The name --goal knows it by
+    fn name(&self) -> &'static str;
+
+    // Shown to the player while the board is being played
+    fn describe(&self) -> String;
+
+    // Cups the goal is tied to, which can't be swapped with another
+    // cup when boards are compared
+    fn keeps_place(&self, _idx: usize) -> bool {
+        false
+    }
+
+    // A lower bound on the pours still needed, zero is always safe
+    fn estimate(&self, _cups: &[Cup]) -> usize {
+        0
+    }
+
+    // Set when the estimate is too rough to steer the search, so the
+    // solver gives up sooner
+    fn estimate_is_rough(&self) -> bool {
+        false
+    }
+}
+
+impl Default for Box<dyn WinCondition> {
+    fn default() -> Self {
+        Box::new(UniqueMonochrome)
+    }
+}
+
+fn is_monochrome(cup: &Cup) -> bool {
+    cup.liquids.iter().all(|l| l == &cup.liquids[0])
+}
+
+fn is_full_of(cup: &Cup, color: &LiquidColors) -> bool {
+    cup.liquids.len() == cup.capacity && cup.liquids.iter().all(|l| l == color)
+}
+
+// Stretches of one color within a cup. Every pour joins at most one
+// pair of runs, so the difference in runs never overestimates.
+fn runs<'a>(cups: impl Iterator<Item = &'a [LiquidColors]>) -> usize {
+    cups.map(|liquids| {
+        liquids
+            .iter()
+            .enumerate()
+            .filter(|(i, l)| *i == 0 || &liquids[i - 1] != *l)
+            .count()
+    })
+    .sum()
+}
+
+// Each pour changes two cups, so every wrong cup needs half a pour
+fn wrong_cups_estimate(wrong: usize) -> usize {
+    wrong.div_ceil(2)
+}
+
+// The classic rule, every color sorted into a cup of its own
+#[derive(Debug)]
+pub struct UniqueMonochrome;
+
+impl WinCondition for UniqueMonochrome {
+    fn name(&self) -> &'static str {
+        "sort"
+    }
+
+    fn is_won(&self, cups: &[Cup]) -> bool {
+        are_cups_solved(cups)
+    }
+
+    fn describe(&self) -> String {
+        "Sort every color into a cup of its own".to_string()
+    }
+
+    // A solved board has exactly one run per color. Ice counts as the
+    // color it melts into and syrup fits into any run, but a shot can
+    // clear several runs at once so boards with shots get no estimate.
+    fn estimate(&self, cups: &[Cup]) -> usize {
+        let units = || cups.iter().flat_map(|c| c.liquids.iter());
+        if units().any(|l| *l == LiquidColors::Shot) {
+            return 0;
+        }
+        let plain = |l: &LiquidColors| match l {
+            LiquidColors::Ice(color, _) => Some((**color).clone()),
+            LiquidColors::Syrup => None,
+            l => Some(l.clone()),
+        };
+        let mut colors: Vec<LiquidColors> = units().filter_map(plain).collect();
+        colors.sort();
+        colors.dedup();
+        let plain_cups: Vec<Vec<LiquidColors>> = cups
+            .iter()
+            .map(|c| c.liquids.iter().filter_map(plain).collect())
+            .collect();
+        runs(plain_cups.iter().map(|c| c.as_slice())) - colors.len()
+    }
+}
+
+// Every cup is either empty or filled to the top with one color.
+// A color with two cups' worth of liquid may fill two cups.
+#[derive(Debug)]
+pub struct FullMonochrome;
+
+impl WinCondition for FullMonochrome {
+    fn name(&self) -> &'static str {
+        "full"
+    }
+
+    fn is_won(&self, cups: &[Cup]) -> bool {
+        cups.iter()
+            .all(|c| c.liquids.is_empty() || (c.liquids.len() == c.capacity && is_monochrome(c)))
+    }
+
+    fn describe(&self) -> String {
+        "Fill every cup you use to the top with a single color".to_string()
+    }
+
+    fn estimate_is_rough(&self) -> bool {
+        true
+    }
+}
+
+// Every cup must hold exactly the given liquids, bottom first
+#[derive(Debug)]
+pub struct TargetLayout {
+    pub target: Vec<Vec<LiquidColors>>,
+}
+
+impl TargetLayout {
+    // Colors lined up left to right in palette order, as many units
+    // per cup as fit, leaving any spare cups empty on the right
+    pub fn sorted(cups: &[Cup]) -> TargetLayout {
+        let mut liquids: Vec<LiquidColors> =
+            cups.iter().flat_map(|c| c.liquids.clone()).collect();
+        liquids.sort();
+        let mut liquids = liquids.into_iter();
+        let target = cups
+            .iter()
+            .map(|c| liquids.by_ref().take(c.capacity).collect())
+            .collect();
+        TargetLayout { target }
+    }
+}
+
+impl WinCondition for TargetLayout {
+    fn name(&self) -> &'static str {
+        "layout"
+    }
+
+    fn is_won(&self, cups: &[Cup]) -> bool {
+        cups.len() == self.target.len()
+            && cups.iter().zip(self.target.iter()).all(|(c, t)| &c.liquids == t)
+    }
+
+    fn describe(&self) -> String {
+        let order: Vec<String> = self
+            .target
+            .iter()
+            .filter_map(|t| t.first())
+            .map(|l| l.to_string().to_lowercase())
+            .collect();
+        format!("Line the colors up left to right: {}", order.join(", "))
+    }
+
+    fn keeps_place(&self, _idx: usize) -> bool {
+        true
+    }
+
+    // Everything above the part of a cup that is already right has to be
+    // poured out, and the rest of its target poured in. Each pour takes
+    // one run out of one cup and puts one run into another. Special
+    // liquids pour more than one run at a time, so they only get the
+    // rougher estimate.
+    fn estimate(&self, cups: &[Cup]) -> usize {
+        let wrong = cups
+            .iter()
+            .zip(self.target.iter())
+            .filter(|(c, t)| &c.liquids != *t)
+            .count();
+        let target_runs = runs(self.target.iter().map(|t| t.as_slice()));
+        let current_runs = runs(cups.iter().map(|c| c.liquids.as_slice()));
+        let rough = wrong_cups_estimate(wrong).max(current_runs.saturating_sub(target_runs));
+        if cups.iter().flat_map(|c| c.liquids.iter()).any(|l| l.is_special()) {
+            return rough;
+        }
+        let (mut pours_out, mut pours_in) = (0, 0);
+        for (cup, target) in cups.iter().zip(self.target.iter()) {
+            let right = cup
+                .liquids
+                .iter()
+                .zip(target.iter())
+                .take_while(|(l, t)| l == t)
+                .count();
+            pours_out += runs([&cup.liquids[right..]].into_iter());
+            pours_in += runs([&target[right..]].into_iter());
+        }
+        rough.max(pours_out).max(pours_in)
+    }
+}
+
+// Only the listed cups matter, each must be filled with its color
+#[derive(Debug)]
+pub struct FillCups {
+    pub targets: Vec<(usize, LiquidColors)>,
+}
+
+impl FillCups {
+    // The first few colors (in palette order) go into the first few cups
+    pub fn first_colors(cups: &[Cup], count: usize) -> FillCups {
+        let mut colors: Vec<LiquidColors> =
+            cups.iter().flat_map(|c| c.liquids.clone()).collect();
+        colors.sort();
+        colors.dedup();
+        FillCups {
+            targets: colors.into_iter().take(count).enumerate().collect(),
+        }
+    }
+}
+
+impl WinCondition for FillCups {
+    fn name(&self) -> &'static str {
+        "fill"
+    }
+
+    fn is_won(&self, cups: &[Cup]) -> bool {
+        self.targets
+            .iter()
+            .all(|(idx, color)| cups.get(*idx).is_some_and(|c| is_full_of(c, color)))
+    }
+
+    fn describe(&self) -> String {
+        let wanted: Vec<String> = self
+            .targets
+            .iter()
+            .map(|(idx, color)| format!("cup {} with {}", idx + 1, color.to_string().to_lowercase()))
+            .collect();
+        format!("Fill {}", wanted.join(", "))
+    }
+
+    fn keeps_place(&self, idx: usize) -> bool {
+        self.targets.iter().any(|(target, _)| *target == idx)
+    }
+
+    // Like a layout, anything above the color at the bottom of a listed
+    // cup has to come out before the cup can be topped up
+    fn estimate(&self, cups: &[Cup]) -> usize {
+        let wrong = self
+            .targets
+            .iter()
+            .filter(|(idx, color)| !cups.get(*idx).is_some_and(|c| is_full_of(c, color)))
+            .count();
+        let rough = wrong_cups_estimate(wrong);
+        if cups.iter().flat_map(|c| c.liquids.iter()).any(|l| l.is_special()) {
+            return rough;
+        }
+        let (mut pours_out, mut pours_in) = (0, 0);
+        for (idx, color) in &self.targets {
+            let Some(cup) = cups.get(*idx) else {
+                continue;
+            };
+            let right = cup.liquids.iter().take_while(|l| *l == color).count();
+            pours_out += runs([&cup.liquids[right..]].into_iter());
+            if right < cup.capacity {
+                pours_in += 1;
+            }
+        }
+        rough.max(pours_out).max(pours_in)
+    }
+}
+
+// Names accepted by --goal. Goals tied to particular cups are built
+// from the board they will be played on.
+pub fn goal_from_name(name: &str, cups: &[Cup]) -> Option<Box<dyn WinCondition>> {
+    match name {
+        "sort" => Some(Box::new(UniqueMonochrome)),
+        "full" => Some(Box::new(FullMonochrome)),
+        "layout" => Some(Box::new(TargetLayout::sorted(cups))),
+        "fill" => Some(Box::new(FillCups::first_colors(cups, 2))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_win_condition {
+    use super::*;
+    use crate::cup::cup;
+    use LiquidColors::{Blue, Green, Red};
+
+    #[test]
+    fn repeated_colors() {
+        // Two cups of red are fine when cups only need to be full
+        let cups = vec![cup(2, vec![Red, Red]), cup(2, vec![Red, Red]), cup(2, vec![])];
+        assert!(!UniqueMonochrome.is_won(&cups));
+        assert!(FullMonochrome.is_won(&cups));
+        assert!(!FullMonochrome.is_won(&[cup(2, vec![Red]), cup(2, vec![Red])]));
+    }
+
+    #[test]
+    fn layouts_depend_on_cup_order() {
+        let cups = vec![cup(2, vec![Green, Red]), cup(2, vec![]), cup(2, vec![Red, Green])];
+        let layout = TargetLayout::sorted(&cups);
+        assert_eq!(layout.target, vec![vec![Red, Red], vec![Green, Green], vec![]]);
+        assert!(!layout.is_won(&cups));
+        assert_eq!(layout.estimate(&cups), 4);
+        assert_eq!(layout.describe(), "Line the colors up left to right: red, green");
+
+        let swapped = vec![cup(2, vec![Green, Green]), cup(2, vec![Red, Red]), cup(2, vec![])];
+        assert!(UniqueMonochrome.is_won(&swapped));
+        assert!(!layout.is_won(&swapped));
+    }
+
+    #[test]
+    fn only_listed_cups_are_checked() {
+        let cups = vec![cup(2, vec![Blue, Green]), cup(2, vec![Green, Blue]), cup(2, vec![])];
+        let fill = FillCups::first_colors(&cups, 1);
+        assert_eq!(fill.targets, vec![(0, Green)]);
+        assert!(fill.is_won(&[cup(2, vec![Green, Green]), cup(2, vec![Blue]), cup(2, vec![Blue])]));
+        assert!(!fill.is_won(&cups));
+    }
+}